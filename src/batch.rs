@@ -0,0 +1,474 @@
+use std::cell::RefCell;
+use std::cmp::Ord;
+use std::rc::Rc;
+
+use crate::observer::Observers;
+use crate::{Event, Link, Tree, TreeNode};
+
+/// Group of puts and deletes applied to tree at once, see `Tree::apply_batch`.
+pub struct WriteBatch<K: Ord, V> {
+    /// Operations in order they were added, None marks deletion.
+    ops: Vec<(K, Option<V>)>,
+}
+
+impl<K: Ord, V> WriteBatch<K, V> {
+    pub fn new() -> Self {
+        WriteBatch { ops: vec![] }
+    }
+
+    /// Adds insertion of key-value.
+    pub fn put(&mut self, key: K, value: V) {
+        self.ops.push((key, Some(value)));
+    }
+
+    /// Adds deletion of key.
+    pub fn delete(&mut self, key: K) {
+        self.ops.push((key, None));
+    }
+
+    /// Returns number of operations in batch.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Checks if batch has no operations.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+impl<K: Ord, V> Default for WriteBatch<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where a run of sorted operations lands in tree.
+enum Target<K: Ord, V> {
+    /// Existing node with the same key.
+    Node(Rc<RefCell<TreeNode<K, V>>>),
+    /// Free child place of given parent, left or right. Root if there is no parent.
+    Place(Link<K, V>, bool),
+}
+
+/// Operation with its position in batch.
+type Op<K, V> = (usize, K, Option<V>);
+
+impl<K: Ord, V> Tree<K, V> {
+    /// Applies all operations of batch, as if they were done one by one in order they were added.
+    /// Operations are sorted and tree is descended once, so shared parts of paths are walked once.
+    /// Returns previous value for every operation, in the original order.
+    /// Nodes are not splayed, even in splay mode.
+    /// Value of deleted node is copied if node is still held from outside.
+    pub fn apply_batch(&mut self, batch: WriteBatch<K, V>) -> Vec<Option<V>>
+    where
+        V: Clone,
+    {
+        let mut ops: Vec<Op<K, V>> = batch
+            .ops
+            .into_iter()
+            .enumerate()
+            .map(|(index, (key, value))| (index, key, value))
+            .collect();
+        // Sort is stable, so operations on the same key keep their order
+        ops.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let targets = self.find_targets(&ops);
+        let mut results: Vec<Option<V>> = ops.iter().map(|_| None).collect();
+        let mut deleted = vec![];
+
+        let mut ops = ops.into_iter();
+        for (len, target) in targets {
+            let run: Vec<_> = ops.by_ref().take(len).collect();
+            match target {
+                Target::Node(node) => {
+                    if let Some(slot) = self.apply_to_node(&node, run, &mut results) {
+                        deleted.push((node, slot));
+                    }
+                }
+                Target::Place(parent, to_left) => {
+                    let entries = settle(run, &mut results, &mut self.observers);
+                    let count = entries.len();
+                    let generation = self.cow.generation();
+                    if let Some(subtree) = build_r(&mut entries.into_iter(), count, generation) {
+                        self.hang(parent, subtree, to_left);
+                    }
+                }
+            }
+        }
+
+        // Deletes go last, relinking would move places found above
+        for (node, slot) in deleted {
+            let detached = self.unlink(&node);
+            drop(node);
+            results[slot] = Some(TreeNode::take_value(detached));
+        }
+        self.finish_change();
+        results
+    }
+
+    /// Descends tree once for all sorted operations, splitting them by nodes they pass.
+    /// Returns length and target of every run of operations, in key order.
+    fn find_targets(&self, ops: &[Op<K, V>]) -> Vec<(usize, Target<K, V>)> {
+        let root = match &self.root {
+            None if ops.is_empty() => return vec![],
+            None => return vec![(ops.len(), Target::Place(None, false))],
+            Some(root) => Rc::clone(root),
+        };
+
+        let mut targets = vec![];
+        let mut stack = vec![(root, 0, ops.len())];
+        while let Some((node, start, end)) = stack.pop() {
+            let run = &ops[start..end];
+            let (less, not_greater, left, right) = {
+                let node = node.borrow();
+                (
+                    start + run.partition_point(|op| op.1 < node.key),
+                    start + run.partition_point(|op| op.1 <= node.key),
+                    node.left.clone(),
+                    node.right.clone(),
+                )
+            };
+
+            if start < less {
+                match left {
+                    Some(left) => stack.push((left, start, less)),
+                    None => targets.push((
+                        start,
+                        less - start,
+                        Target::Place(Some(Rc::clone(&node)), true),
+                    )),
+                }
+            }
+            if less < not_greater {
+                targets.push((less, not_greater - less, Target::Node(Rc::clone(&node))));
+            }
+            if not_greater < end {
+                match right {
+                    Some(right) => stack.push((right, not_greater, end)),
+                    None => targets.push((
+                        not_greater,
+                        end - not_greater,
+                        Target::Place(Some(node), false),
+                    )),
+                }
+            }
+        }
+
+        targets.sort_by_key(|(start, _, _)| *start);
+        targets
+            .into_iter()
+            .map(|(_, len, target)| (len, target))
+            .collect()
+    }
+
+    /// Applies run of operations on key of existing node.
+    /// Returns position of operation whose result waits for node to be deleted, if node must go.
+    fn apply_to_node(
+        &mut self,
+        node: &Rc<RefCell<TreeNode<K, V>>>,
+        run: Vec<Op<K, V>>,
+        results: &mut [Option<V>],
+    ) -> Option<usize> {
+        let mut owned = None;
+        let mut deleted_at = None;
+
+        for (index, _, value) in run {
+            match value {
+                Some(value) => {
+                    let node = owned.get_or_insert_with(|| self.own(node));
+                    let previous = std::mem::replace(&mut node.borrow_mut().value, value);
+
+                    let node = node.borrow();
+                    match deleted_at.take() {
+                        // Deleted value was still in node, it is previous value of deletion
+                        Some(slot) => {
+                            self.observers.notify(&Event::Inserted {
+                                key: &node.key,
+                                value: &node.value,
+                            });
+                            results[slot] = Some(previous);
+                        }
+                        None => {
+                            self.observers.notify(&Event::Replaced {
+                                key: &node.key,
+                                old: &previous,
+                                new: &node.value,
+                            });
+                            results[index] = Some(previous);
+                        }
+                    }
+                }
+                None if deleted_at.is_none() => {
+                    let node = owned.as_ref().unwrap_or(node).borrow();
+                    self.observers.notify(&Event::Removed {
+                        key: &node.key,
+                        value: &node.value,
+                    });
+                    deleted_at = Some(index);
+                }
+                None => {}
+            }
+        }
+        deleted_at
+    }
+}
+
+/// Applies run of operations on keys missing from tree.
+/// Returns key-values that survive, in key order.
+fn settle<K: Ord, V>(
+    run: Vec<Op<K, V>>,
+    results: &mut [Option<V>],
+    observers: &mut Observers<K, V>,
+) -> Vec<(K, V)> {
+    let mut settled: Vec<(K, Option<V>)> = vec![];
+    for (index, key, value) in run {
+        if settled.last().map_or(true, |(last, _)| *last != key) {
+            settled.push((key, None));
+        }
+        let (key, current) = settled.last_mut().unwrap();
+
+        results[index] = match value {
+            Some(value) => {
+                let previous = current.replace(value);
+                let new = current.as_ref().unwrap();
+                match &previous {
+                    None => observers.notify(&Event::Inserted { key, value: new }),
+                    Some(old) => observers.notify(&Event::Replaced { key, old, new }),
+                }
+                previous
+            }
+            None => {
+                let previous = current.take();
+                if let Some(value) = &previous {
+                    observers.notify(&Event::Removed { key, value });
+                }
+                previous
+            }
+        };
+    }
+
+    settled
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+}
+
+/// Builds balanced subtree of next `count` sorted key-values.
+pub(crate) fn build_r<K: Ord, V>(
+    entries: &mut impl Iterator<Item = (K, V)>,
+    count: usize,
+    generation: u64,
+) -> Link<K, V> {
+    if count == 0 {
+        return None;
+    }
+
+    let left = build_r(entries, count / 2, generation);
+    let (key, value) = entries.next().unwrap();
+    let right = build_r(entries, count - count / 2 - 1, generation);
+
+    let mut node = TreeNode::new(key, value);
+    node.generation = generation;
+    node.count = count;
+    node.left = left.clone();
+    node.right = right.clone();
+    let node = Rc::new(RefCell::new(node));
+
+    for child in left.iter().chain(right.iter()) {
+        child.borrow_mut().parent = Some(Rc::clone(&node));
+    }
+    Some(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WriteBatch;
+    use crate::{Mode, Tree};
+
+    fn balanced_tree(mode: Mode) -> Tree<i64, i64> {
+        let mut tree = Tree::with_mode(mode);
+        for key in &[100, 50, 200, 30, 70, 150, 300] {
+            tree.insert(*key, *key);
+        }
+        tree
+    }
+
+    fn entries(tree: Tree<i64, i64>) -> Vec<(i64, i64)> {
+        tree.iter_node()
+            .map(|node| (node.borrow().key, node.borrow().value))
+            .collect()
+    }
+
+    #[test]
+    fn results_in_original_order() {
+        let mut tree = balanced_tree(Mode::Unbalanced);
+        let mut batch = WriteBatch::new();
+        batch.put(300, 3);
+        batch.delete(50);
+        batch.put(10, 1);
+        batch.delete(60);
+        batch.put(100, 10);
+        assert_eq!(batch.len(), 5);
+
+        let results = tree.apply_batch(batch);
+        assert_eq!(results, vec![Some(300), Some(50), None, None, Some(100)]);
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(
+            entries(tree),
+            vec![
+                (10, 1),
+                (30, 30),
+                (70, 70),
+                (100, 10),
+                (150, 150),
+                (200, 200),
+                (300, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn same_key_in_order() {
+        let mut tree = balanced_tree(Mode::Unbalanced);
+        let mut batch = WriteBatch::new();
+        // Existing key deleted, put back and deleted again
+        batch.delete(70);
+        batch.put(70, 1);
+        batch.delete(70);
+        batch.delete(70);
+        // Missing key put twice and deleted
+        batch.put(80, 2);
+        batch.put(80, 3);
+        batch.delete(80);
+        batch.put(80, 4);
+        // Existing key deleted and put back
+        batch.delete(200);
+        batch.put(200, 5);
+
+        let results = tree.apply_batch(batch);
+        assert_eq!(
+            results,
+            vec![
+                Some(70),
+                None,
+                Some(1),
+                None,
+                None,
+                Some(2),
+                Some(3),
+                None,
+                Some(200),
+                None
+            ]
+        );
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.get(&70), None);
+        assert_eq!(tree.get(&80), Some(4));
+        assert_eq!(tree.get(&200), Some(5));
+        assert_eq!(tree.len(), 7);
+    }
+
+    #[test]
+    fn empty_tree_and_batch() {
+        let mut tree = Tree::new();
+        assert!(tree.apply_batch(WriteBatch::new()).is_empty());
+
+        let mut batch = WriteBatch::new();
+        for key in (0..100).rev() {
+            batch.put(key, key * 2);
+        }
+        batch.delete(50);
+        tree.apply_batch(batch);
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.len(), 99);
+        assert_eq!(tree.get(&99), Some(198));
+        assert_eq!(tree.get(&50), None);
+    }
+
+    #[test]
+    fn deleted_node_held_outside() {
+        let mut tree = balanced_tree(Mode::Unbalanced);
+        let held = tree.find_node(&50).unwrap();
+        let mut batch = WriteBatch::new();
+        batch.delete(50);
+        batch.delete(100);
+        assert_eq!(tree.apply_batch(batch), vec![Some(50), Some(100)]);
+        assert_eq!(tree.validate(), Ok(()));
+
+        // Handle keeps its copy of entry, but is cut from tree
+        let node = held.borrow();
+        assert_eq!((node.key, node.value), (50, 50));
+        assert!(node.parent.is_none() && node.left.is_none() && node.right.is_none());
+    }
+
+    #[test]
+    fn snapshot_untouched() {
+        let mut tree = balanced_tree(Mode::Splay);
+        let snapshot = tree.snapshot();
+        let mut batch = WriteBatch::new();
+        batch.delete(100);
+        batch.delete(30);
+        batch.put(70, 7);
+        batch.put(250, 250);
+        tree.apply_batch(batch);
+        assert_eq!(tree.validate(), Ok(()));
+
+        let seen: Vec<_> = snapshot.iter().collect();
+        assert_eq!(
+            seen,
+            vec![
+                (30, 30),
+                (50, 50),
+                (70, 70),
+                (100, 100),
+                (150, 150),
+                (200, 200),
+                (300, 300)
+            ]
+        );
+        assert_eq!(
+            entries(tree),
+            vec![
+                (50, 50),
+                (70, 7),
+                (150, 150),
+                (200, 200),
+                (250, 250),
+                (300, 300)
+            ]
+        );
+    }
+
+    #[test]
+    fn random_batches_against_model() {
+        let mut seed: u64 = 7;
+        let mut next = move || {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) as i64
+        };
+
+        let mut tree = Tree::new();
+        let mut model = Tree::new();
+        for _ in 0..50 {
+            let mut batch = WriteBatch::new();
+            let mut expected = vec![];
+            for _ in 0..40 {
+                let key = next() % 100;
+                if next() % 3 == 0 {
+                    batch.delete(key);
+                    expected.push(model.remove_value(&key));
+                } else {
+                    let value = next();
+                    batch.put(key, value);
+                    expected.push(model.insert(key, value));
+                }
+            }
+
+            assert_eq!(tree.apply_batch(batch), expected);
+            assert_eq!(tree.validate(), Ok(()));
+            assert_eq!(tree.len(), model.len());
+        }
+        assert_eq!(entries(tree), entries(model));
+    }
+}
@@ -0,0 +1,343 @@
+use std::cmp::Ord;
+use std::convert::{TryFrom, TryInto};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::batch::build_r;
+use crate::{Mode, Tree, TreeNodeIterator};
+
+/// First bytes of every saved tree.
+const MAGIC: &[u8; 4] = b"MLTR";
+
+/// Version of format written by `Tree::write_to`.
+const VERSION: u16 = 1;
+
+/// Turns key or value into bytes for `Tree::write_to`.
+pub trait Encode {
+    /// Appends bytes of self to buffer.
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
+/// Restores key or value from bytes written by `Encode`.
+pub trait Decode: Sized {
+    /// Returns None if bytes are not a valid encoding.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+
+            impl Decode for $t {
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    Some(<$t>::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_number!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl Encode for bool {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl Encode for Vec<u8> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+}
+
+impl Decode for Vec<u8> {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+/// Reasons `Tree::read_from` may fail.
+#[derive(Debug)]
+pub enum FormatError {
+    Io(io::Error),
+    /// Data does not start with magic bytes, it is not a saved tree.
+    BadMagic,
+    /// Tree was saved with a format version other than the one this build reads.
+    UnsupportedVersion(u16),
+    /// Key or value bytes were rejected by `Decode`.
+    BadRecord,
+    /// Keys are not strictly increasing.
+    Unordered,
+    /// Saved shape has wrong markers, misses nodes or has data after the last one.
+    BadShape,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Io(err) => write!(f, "io error: {}", err),
+            FormatError::BadMagic => write!(f, "not a saved tree"),
+            FormatError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            FormatError::BadRecord => write!(f, "key or value can not be decoded"),
+            FormatError::Unordered => write!(f, "keys are not in increasing order"),
+            FormatError::BadShape => write!(f, "broken tree shape"),
+        }
+    }
+}
+
+impl Error for FormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FormatError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FormatError {
+    fn from(err: io::Error) -> Self {
+        FormatError::Io(err)
+    }
+}
+
+impl<K: Ord + Encode, V: Encode> Tree<K, V> {
+    /// Saves all entries of tree. Mode is not saved.
+    ///
+    /// Format, all numbers little endian:
+    /// - magic bytes `MLTR`;
+    /// - format version, u16;
+    /// - number of entries, u64;
+    /// - for every entry in key order: key length as u32, key bytes, value length as u32, value bytes.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.size as u64).to_le_bytes())?;
+
+        let mut buf = vec![];
+        let mut current = self.least_node();
+        while let Some(node) = current {
+            {
+                let node = node.borrow();
+                write_record(&mut writer, &mut buf, &node.key)?;
+                write_record(&mut writer, &mut buf, &node.value)?;
+            }
+            current = TreeNodeIterator::next_of(&node);
+        }
+        writer.flush()
+    }
+}
+
+impl<K: Ord + Decode, V: Decode> Tree<K, V> {
+    /// Loads tree saved by `write_to`, in linear time. Loaded tree is balanced.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, FormatError> {
+        Self::read_with_mode(reader, Mode::Unbalanced)
+    }
+
+    /// Loads tree saved by `write_to`, giving it mode.
+    pub fn read_with_mode<R: Read>(mut reader: R, mode: Mode) -> Result<Self, FormatError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+
+        let mut version = [0; 2];
+        reader.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+
+        let mut count = [0; 8];
+        reader.read_exact(&mut count)?;
+        let count = u64::from_le_bytes(count) as usize;
+
+        // Count is not trusted for allocation, broken data would eat all memory
+        let mut entries: Vec<(K, V)> = Vec::with_capacity(count.min(4096));
+        let mut buf = vec![];
+        for _ in 0..count {
+            let key: K = read_record(&mut reader, &mut buf)?;
+            let value: V = read_record(&mut reader, &mut buf)?;
+            if entries.last().is_some_and(|(last, _)| *last >= key) {
+                return Err(FormatError::Unordered);
+            }
+            entries.push((key, value));
+        }
+
+        let mut tree = Tree::with_mode(mode);
+        let generation = tree.cow.generation();
+        tree.root = build_r(&mut entries.into_iter(), count, generation);
+        tree.size = count;
+        Ok(tree)
+    }
+}
+
+pub(crate) fn write_record<W: Write, T: Encode>(
+    writer: &mut W,
+    buf: &mut Vec<u8>,
+    item: &T,
+) -> io::Result<()> {
+    buf.clear();
+    item.encode(buf);
+    let len = u32::try_from(buf.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "record is longer than u32"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(buf)
+}
+
+pub(crate) fn read_record<R: Read, T: Decode>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+) -> Result<T, FormatError> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+
+    buf.clear();
+    reader.take(len as u64).read_to_end(buf)?;
+    if buf.len() != len {
+        return Err(FormatError::Io(io::ErrorKind::UnexpectedEof.into()));
+    }
+    T::decode(buf).ok_or(FormatError::BadRecord)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FormatError;
+    use crate::{Mode, Tree};
+
+    fn saved(tree: &Tree<i64, String>) -> Vec<u8> {
+        let mut bytes = vec![];
+        tree.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn entries(tree: Tree<i64, String>) -> Vec<(i64, String)> {
+        tree.into_entries()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut tree = Tree::new();
+        for key in &[100, 50, 200, -30, 70, 150, 300] {
+            tree.insert(*key, format!("v{}", key));
+        }
+        let bytes = saved(&tree);
+
+        let loaded: Tree<i64, String> = Tree::read_from(&bytes[..]).unwrap();
+        assert_eq!(loaded.len(), 7);
+        assert_eq!(loaded.get(&-30), Some("v-30".to_string()));
+        assert_eq!(entries(loaded), entries(tree));
+
+        let mut loaded: Tree<i64, String> = Tree::read_with_mode(&bytes[..], Mode::Splay).unwrap();
+        assert_eq!(loaded.mode(), Mode::Splay);
+        loaded.insert(1, "one".to_string());
+        assert_eq!(loaded.len(), 8);
+    }
+
+    #[test]
+    fn layout() {
+        let mut tree = Tree::new();
+        tree.insert(1u8, true);
+        let mut bytes = vec![];
+        tree.write_to(&mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            vec![
+                b'M', b'L', b'T', b'R', 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0, 0, 1
+            ]
+        );
+    }
+
+    #[test]
+    fn balanced_build() {
+        let mut tree = Tree::new();
+        for key in 0..1000 {
+            tree.insert(key, String::new());
+        }
+        let loaded: Tree<i64, String> = Tree::read_from(&saved(&tree)[..]).unwrap();
+
+        fn height(node: &crate::Link<i64, String>) -> usize {
+            match node {
+                None => 0,
+                Some(node) => {
+                    let node = node.borrow();
+                    1 + height(&node.left).max(height(&node.right))
+                }
+            }
+        }
+        assert_eq!(height(&loaded.root), 10);
+        assert_eq!(loaded.root.as_ref().unwrap().borrow().count, 1000);
+    }
+
+    #[test]
+    fn empty() {
+        let loaded: Tree<i64, String> = Tree::read_from(&saved(&Tree::new())[..]).unwrap();
+        assert!(loaded.is_empty());
+    }
+
+    #[test]
+    fn broken_data() {
+        let mut tree = Tree::new();
+        tree.insert(1, "one".to_string());
+        tree.insert(2, "two".to_string());
+        let bytes = saved(&tree);
+
+        let read = |bytes: &[u8]| Tree::<i64, String>::read_from(bytes).err().unwrap();
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert!(matches!(read(&bad), FormatError::BadMagic));
+
+        let mut bad = bytes.clone();
+        bad[4] = 2;
+        assert!(matches!(read(&bad), FormatError::UnsupportedVersion(2)));
+        bad[4] = 0;
+        assert!(matches!(read(&bad), FormatError::UnsupportedVersion(0)));
+
+        assert!(matches!(
+            read(&bytes[..bytes.len() - 1]),
+            FormatError::Io(_)
+        ));
+
+        // Value of the second entry is not utf-8
+        let mut bad = bytes.clone();
+        let last = bad.len() - 1;
+        bad[last] = 0xff;
+        assert!(matches!(read(&bad), FormatError::BadRecord));
+
+        // Key of the first entry says 5
+        let mut bad = bytes.clone();
+        bad[18] = 5;
+        assert!(matches!(read(&bad), FormatError::Unordered));
+    }
+}
@@ -0,0 +1,415 @@
+use std::cell::{Ref, RefCell, RefMut};
+use std::cmp::{Ord, Ordering};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::snapshot::resolve;
+use crate::{Link, Tree, TreeNode, TreeNodeIterator};
+
+/// Cursors: positioned navigation over tree.
+/// Cursor points either to a node or to the "ghost" position past the greatest node.
+/// Stepping from ghost wraps around to the least (or greatest) node.
+impl<K: Ord, V> Tree<K, V> {
+    /// Returns cursor at first node with key greater or equal to given one.
+    pub fn lower_bound(&self, key: &K) -> Cursor<'_, K, V> {
+        Cursor {
+            current: self.bound_node(key, true),
+            tree: self,
+        }
+    }
+
+    /// Returns cursor at first node with key strictly greater than given one.
+    pub fn upper_bound(&self, key: &K) -> Cursor<'_, K, V> {
+        Cursor {
+            current: self.bound_node(key, false),
+            tree: self,
+        }
+    }
+
+    /// Returns cursor at the least node.
+    pub fn cursor_front(&self) -> Cursor<'_, K, V> {
+        Cursor {
+            current: self.least_node(),
+            tree: self,
+        }
+    }
+
+    /// Returns cursor at the greatest node.
+    pub fn cursor_back(&self) -> Cursor<'_, K, V> {
+        Cursor {
+            current: self.greatest_node(),
+            tree: self,
+        }
+    }
+
+    /// Same as `lower_bound`, but cursor can change tree.
+    pub fn lower_bound_mut(&mut self, key: &K) -> CursorMut<'_, K, V> {
+        CursorMut {
+            current: self.bound_node(key, true),
+            tree: self,
+        }
+    }
+
+    /// Same as `upper_bound`, but cursor can change tree.
+    pub fn upper_bound_mut(&mut self, key: &K) -> CursorMut<'_, K, V> {
+        CursorMut {
+            current: self.bound_node(key, false),
+            tree: self,
+        }
+    }
+
+    /// Descends looking for least key that is greater (or equal, if inclusive) than given one.
+    fn bound_node(&self, key: &K, inclusive: bool) -> Link<K, V> {
+        let mut found = None;
+        let mut current = self.root.clone();
+
+        while let Some(node_ref) = current {
+            let node = node_ref.borrow();
+            let fits = match node.key.cmp(key) {
+                Ordering::Greater => true,
+                Ordering::Equal => inclusive,
+                Ordering::Less => false,
+            };
+
+            if fits {
+                current = node.left.clone();
+                drop(node);
+                found = Some(node_ref);
+            } else {
+                current = node.right.clone();
+            }
+        }
+        found
+    }
+}
+
+/// Read only cursor, see `Tree::lower_bound`.
+pub struct Cursor<'a, K: Ord, V> {
+    tree: &'a Tree<K, V>,
+    /// None stands for ghost position.
+    current: Link<K, V>,
+}
+
+/// Cursor that can also insert and remove nodes, see `Tree::lower_bound_mut`.
+pub struct CursorMut<'a, K: Ord, V> {
+    tree: &'a mut Tree<K, V>,
+    /// None stands for ghost position.
+    current: Link<K, V>,
+}
+
+/// Error of cursor insertion, when new key does not fit between its neighbours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnorderedKeyError;
+
+impl fmt::Display for UnorderedKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key does not fit between cursor neighbours")
+    }
+}
+
+impl Error for UnorderedKeyError {}
+
+/// Both cursors move the same way.
+fn step_next<K: Ord, V>(tree: &Tree<K, V>, current: &Link<K, V>) -> Link<K, V> {
+    match current {
+        None => tree.least_node(),
+        Some(node) => TreeNodeIterator::next_of(node),
+    }
+}
+
+fn step_prev<K: Ord, V>(tree: &Tree<K, V>, current: &Link<K, V>) -> Link<K, V> {
+    match current {
+        None => tree.greatest_node(),
+        Some(node) => TreeNodeIterator::prev_of(node),
+    }
+}
+
+impl<'a, K: Ord, V> Cursor<'a, K, V> {
+    /// Returns node under cursor, None at ghost position.
+    pub fn node(&self) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
+        self.current.clone()
+    }
+
+    /// Returns key under cursor.
+    pub fn key(&self) -> Option<Ref<'_, K>> {
+        let node = self.current.as_ref()?;
+        Some(Ref::map(node.borrow(), |n| &n.key))
+    }
+
+    /// Returns value under cursor.
+    pub fn value(&self) -> Option<Ref<'_, V>> {
+        let node = self.current.as_ref()?;
+        Some(Ref::map(node.borrow(), |n| &n.value))
+    }
+
+    /// Moves cursor to the next node.
+    pub fn move_next(&mut self) {
+        self.current = step_next(self.tree, &self.current);
+    }
+
+    /// Moves cursor to the previous node.
+    pub fn move_prev(&mut self) {
+        self.current = step_prev(self.tree, &self.current);
+    }
+
+    /// Returns next node without moving.
+    pub fn peek_next(&self) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
+        step_next(self.tree, &self.current)
+    }
+
+    /// Returns previous node without moving.
+    pub fn peek_prev(&self) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
+        step_prev(self.tree, &self.current)
+    }
+}
+
+impl<'a, K: Ord, V> CursorMut<'a, K, V> {
+    /// Returns node under cursor, None at ghost position.
+    pub fn node(&self) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
+        self.current.clone()
+    }
+
+    /// Returns key under cursor.
+    pub fn key(&self) -> Option<Ref<'_, K>> {
+        let node = self.current.as_ref()?;
+        Some(Ref::map(node.borrow(), |n| &n.key))
+    }
+
+    /// Returns value under cursor.
+    pub fn value(&self) -> Option<Ref<'_, V>> {
+        let node = self.current.as_ref()?;
+        Some(Ref::map(node.borrow(), |n| &n.value))
+    }
+
+    /// Returns mutable value under cursor. Key can not be changed.
+    pub fn value_mut(&mut self) -> Option<RefMut<'_, V>> {
+        let node = self.tree.own(self.current.as_ref()?);
+        self.current = Some(node);
+
+        let node = self.current.as_ref().unwrap();
+        Some(RefMut::map(node.borrow_mut(), |n| &mut n.value))
+    }
+
+    /// Moves cursor to the next node.
+    pub fn move_next(&mut self) {
+        self.current = step_next(self.tree, &self.current);
+    }
+
+    /// Moves cursor to the previous node.
+    pub fn move_prev(&mut self) {
+        self.current = step_prev(self.tree, &self.current);
+    }
+
+    /// Returns next node without moving.
+    pub fn peek_next(&self) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
+        step_next(self.tree, &self.current)
+    }
+
+    /// Returns previous node without moving.
+    pub fn peek_prev(&self) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
+        step_prev(self.tree, &self.current)
+    }
+
+    /// Removes node under cursor and moves cursor to the next one.
+    /// Returns removed node, nothing happens at ghost position.
+    pub fn remove_current(&mut self) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
+        let node = self.current.take()?;
+        self.current = TreeNodeIterator::next_of(&node);
+        let node = self.tree.unlink(&node);
+        self.refresh();
+        self.tree.notify_removed(&node);
+        self.tree.finish_change();
+        Some(node)
+    }
+
+    /// Follows current node to its copy, if changes of tree made one for snapshots.
+    /// Called after every change, so cursor never holds outdated node.
+    fn refresh(&mut self) {
+        self.current = self.current.as_ref().map(resolve);
+    }
+
+    /// Inserts new node right after cursor, cursor stays in place.
+    /// At ghost position node becomes the least one.
+    /// Fails if key is not strictly between current and next keys.
+    pub fn insert_after(&mut self, key: K, value: V) -> Result<(), UnorderedKeyError> {
+        let next = self.peek_next();
+        if !fits(&self.current, &key, &next) {
+            return Err(UnorderedKeyError);
+        }
+
+        let new_node = TreeNode::new(key, value);
+        let node = match &self.current {
+            // Before the least node, which has no left child
+            None => self.tree.attach(next, new_node, true),
+            Some(current) => {
+                if current.borrow().right.is_none() {
+                    self.tree.attach(Some(Rc::clone(current)), new_node, false)
+                } else {
+                    // Next node is the least in right subtree, so its left side is free
+                    self.tree.attach(next, new_node, true)
+                }
+            }
+        };
+        self.refresh();
+        self.tree.notify_inserted(&node);
+        self.tree.finish_change();
+        Ok(())
+    }
+
+    /// Inserts new node right before cursor, cursor stays in place.
+    /// At ghost position node becomes the greatest one.
+    /// Fails if key is not strictly between previous and current keys.
+    pub fn insert_before(&mut self, key: K, value: V) -> Result<(), UnorderedKeyError> {
+        let prev = self.peek_prev();
+        if !fits(&prev, &key, &self.current) {
+            return Err(UnorderedKeyError);
+        }
+
+        let new_node = TreeNode::new(key, value);
+        let node = match &self.current {
+            // After the greatest node, which has no right child
+            None => self.tree.attach(prev, new_node, false),
+            Some(current) => {
+                if current.borrow().left.is_none() {
+                    self.tree.attach(Some(Rc::clone(current)), new_node, true)
+                } else {
+                    // Previous node is the greatest in left subtree, so its right side is free
+                    self.tree.attach(prev, new_node, false)
+                }
+            }
+        };
+        self.refresh();
+        self.tree.notify_inserted(&node);
+        self.tree.finish_change();
+        Ok(())
+    }
+}
+
+/// Checks if key is strictly between two optional neighbours.
+fn fits<K: Ord, V>(lower: &Link<K, V>, key: &K, upper: &Link<K, V>) -> bool {
+    let above_lower = match lower {
+        None => true,
+        Some(lower) => lower.borrow().key.cmp(key) == Ordering::Less,
+    };
+    let below_upper = match upper {
+        None => true,
+        Some(upper) => upper.borrow().key.cmp(key) == Ordering::Greater,
+    };
+    above_lower && below_upper
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{keys, paper_tree};
+    use crate::{Mode, Tree, UnorderedKeyError};
+
+    #[test]
+    fn bounds() {
+        let tree = paper_tree(Mode::Unbalanced);
+        assert_eq!(*tree.lower_bound(&70).key().unwrap(), 70);
+        assert_eq!(*tree.upper_bound(&70).key().unwrap(), 99);
+        assert_eq!(*tree.lower_bound(&71).key().unwrap(), 99);
+        assert_eq!(*tree.lower_bound(&0).key().unwrap(), 10);
+        assert!(tree.lower_bound(&301).key().is_none());
+        assert!(tree.upper_bound(&300).node().is_none());
+    }
+
+    #[test]
+    fn front_back() {
+        let tree = paper_tree(Mode::Unbalanced);
+        assert_eq!(*tree.cursor_front().key().unwrap(), 10);
+        assert_eq!(*tree.cursor_back().key().unwrap(), 300);
+
+        let empty: Tree<i64, i64> = Tree::new();
+        assert!(empty.cursor_front().key().is_none());
+    }
+
+    #[test]
+    fn walk_both_ways() {
+        let tree = paper_tree(Mode::Unbalanced);
+        let mut cursor = tree.lower_bound(&60);
+
+        let mut forward = vec![];
+        while let Some(key) = cursor.key().map(|k| *k) {
+            forward.push(key);
+            cursor.move_next();
+        }
+        assert_eq!(forward, vec![60, 70, 99, 100, 115, 200, 300]);
+
+        // From ghost we wrap to the greatest
+        cursor.move_prev();
+        assert_eq!(*cursor.key().unwrap(), 300);
+        assert_eq!(cursor.peek_prev().unwrap().borrow().value, 200);
+        assert!(cursor.peek_next().is_none());
+
+        let mut backward = vec![];
+        while let Some(key) = cursor.key().map(|k| *k) {
+            backward.push(key);
+            cursor.move_prev();
+        }
+        assert_eq!(backward, vec![300, 200, 115, 100, 99, 70, 60, 50, 10]);
+
+        cursor.move_next();
+        assert_eq!(*cursor.key().unwrap(), 10);
+    }
+
+    #[test]
+    fn remove_while_walking() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let mut cursor = tree.lower_bound_mut(&60);
+
+        assert_eq!(cursor.remove_current().unwrap().borrow().key, 60);
+        assert_eq!(*cursor.key().unwrap(), 70);
+        cursor.move_next();
+        assert_eq!(cursor.remove_current().unwrap().borrow().key, 99);
+        assert_eq!(cursor.remove_current().unwrap().borrow().key, 100);
+        assert_eq!(*cursor.key().unwrap(), 115);
+
+        assert_eq!(tree.len(), 6);
+        assert_eq!(keys(tree), vec![10, 50, 70, 115, 200, 300]);
+    }
+
+    #[test]
+    fn insert_around() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let mut cursor = tree.lower_bound_mut(&100);
+
+        assert_eq!(cursor.insert_before(99, 0), Err(UnorderedKeyError));
+        assert_eq!(cursor.insert_before(100, 0), Err(UnorderedKeyError));
+        assert_eq!(cursor.insert_after(100, 0), Err(UnorderedKeyError));
+        assert_eq!(cursor.insert_after(115, 0), Err(UnorderedKeyError));
+        assert_eq!(cursor.insert_after(50, 0), Err(UnorderedKeyError));
+
+        cursor.insert_after(101, 101).unwrap();
+        *cursor.value_mut().unwrap() = 1000;
+        assert_eq!(*cursor.key().unwrap(), 100);
+        assert_eq!(cursor.peek_next().unwrap().borrow().key, 101);
+
+        cursor.move_prev();
+        cursor.insert_before(98, 98).unwrap();
+        assert_eq!(*cursor.key().unwrap(), 99);
+        assert_eq!(cursor.peek_prev().unwrap().borrow().key, 98);
+
+        assert_eq!(tree.len(), 11);
+        assert_eq!(tree.get(&100), Some(1000));
+        assert_eq!(
+            keys(tree),
+            vec![10, 50, 60, 70, 98, 99, 100, 101, 115, 200, 300]
+        );
+    }
+
+    #[test]
+    fn insert_at_ghost() {
+        let mut tree = Tree::new();
+        let mut cursor = tree.lower_bound_mut(&0);
+
+        cursor.insert_before(5, 5).unwrap();
+        cursor.insert_before(7, 7).unwrap();
+        cursor.insert_after(1, 1).unwrap();
+        assert_eq!(cursor.insert_after(6, 6), Err(UnorderedKeyError));
+        assert!(cursor.key().is_none());
+
+        assert_eq!(keys(tree), vec![1, 5, 7]);
+    }
+}
@@ -0,0 +1,178 @@
+use std::cmp::Ord;
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+use std::rc::Rc;
+
+use crate::Tree;
+
+/// What `Tree::to_dot_with` draws besides nodes and child edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DotOptions {
+    /// Dashed edges from every node to its parent, as parent links say.
+    pub parent_edges: bool,
+    /// Point placeholders for missing children.
+    pub null_children: bool,
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        DotOptions {
+            parent_edges: false,
+            null_children: true,
+        }
+    }
+}
+
+impl<K: Ord, V> Tree<K, V> {
+    /// Returns Graphviz graph of tree, nodes labelled as `key=value`.
+    pub fn to_dot(&self) -> String
+    where
+        K: Display,
+        V: Display,
+    {
+        self.to_dot_with(DotOptions::default(), |key, value| {
+            format!("{}={}", key, value)
+        })
+    }
+
+    /// Returns Graphviz graph of tree, labelling nodes with given function.
+    pub fn to_dot_with<F>(&self, options: DotOptions, label: F) -> String
+    where
+        F: Fn(&K, &V) -> String,
+    {
+        let mut dot = String::from("digraph Tree {\n");
+        let mut ids = HashMap::new();
+        let mut nulls = 0;
+
+        // Pre-order, so node is numbered before its children
+        let mut stack = vec![(self.root.clone(), None, "")];
+        let mut nodes = vec![];
+        while let Some((link, parent, side)) = stack.pop() {
+            match link {
+                None if options.null_children && parent.is_some() => {
+                    let _ = writeln!(dot, "    null{} [shape=point];", nulls);
+                    let _ = writeln!(
+                        dot,
+                        "    n{} -> null{} [label=\"{}\"];",
+                        parent.unwrap(),
+                        nulls,
+                        side
+                    );
+                    nulls += 1;
+                }
+                None => {}
+                Some(node) => {
+                    let id = ids.len();
+                    ids.insert(Rc::as_ptr(&node), id);
+                    {
+                        let node = node.borrow();
+                        let text = escape(&label(&node.key, &node.value));
+                        let _ = writeln!(dot, "    n{} [label=\"{}\"];", id, text);
+                        stack.push((node.right.clone(), Some(id), "R"));
+                        stack.push((node.left.clone(), Some(id), "L"));
+                    }
+                    if let Some(parent) = parent {
+                        let _ = writeln!(dot, "    n{} -> n{} [label=\"{}\"];", parent, id, side);
+                    }
+                    nodes.push((id, node));
+                }
+            }
+        }
+
+        if options.parent_edges {
+            for (id, node) in nodes {
+                let parent = match &node.borrow().parent {
+                    None => continue,
+                    Some(parent) => Rc::as_ptr(parent),
+                };
+                match ids.get(&parent) {
+                    Some(parent) => {
+                        let _ = writeln!(
+                            dot,
+                            "    n{} -> n{} [style=dashed, constraint=false];",
+                            id, parent
+                        );
+                    }
+                    // Parent link points out of tree, which is a bug worth seeing
+                    None => {
+                        let _ = writeln!(dot, "    lost{} [label=\"?\", color=red];", id);
+                        let _ =
+                            writeln!(dot, "    n{} -> lost{} [style=dashed, color=red];", id, id);
+                    }
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Escapes label for a quoted DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DotOptions;
+    use crate::Tree;
+    use std::rc::Rc;
+
+    fn small_tree() -> Tree<i64, &'static str> {
+        let mut tree = Tree::new();
+        tree.insert(2, "two");
+        tree.insert(1, "one");
+        tree.insert(3, "\"three\"");
+        tree
+    }
+
+    #[test]
+    fn default_graph() {
+        let dot = small_tree().to_dot();
+        assert_eq!(
+            dot,
+            "digraph Tree {
+    n0 [label=\"2=two\"];
+    n1 [label=\"1=one\"];
+    n0 -> n1 [label=\"L\"];
+    null0 [shape=point];
+    n1 -> null0 [label=\"L\"];
+    null1 [shape=point];
+    n1 -> null1 [label=\"R\"];
+    n2 [label=\"3=\\\"three\\\"\"];
+    n0 -> n2 [label=\"R\"];
+    null2 [shape=point];
+    n2 -> null2 [label=\"L\"];
+    null3 [shape=point];
+    n2 -> null3 [label=\"R\"];
+}
+"
+        );
+        assert_eq!(Tree::<i64, i64>::new().to_dot(), "digraph Tree {\n}\n");
+    }
+
+    #[test]
+    fn parent_edges_and_labels() {
+        let tree = small_tree();
+        let options = DotOptions {
+            parent_edges: true,
+            null_children: false,
+        };
+        let dot = tree.to_dot_with(options, |key, _| key.to_string());
+        assert!(!dot.contains("null"));
+        assert!(dot.contains("n1 [label=\"1\"];"));
+        assert!(dot.contains("n1 -> n0 [style=dashed, constraint=false];"));
+        assert!(dot.contains("n2 -> n0 [style=dashed, constraint=false];"));
+        assert!(!dot.contains("lost"));
+
+        // Broken parent link is drawn in red
+        let mut stranger = Tree::new();
+        stranger.insert(10, "ten");
+        let left = tree.root.as_ref().unwrap().borrow().left.clone().unwrap();
+        left.borrow_mut().parent = stranger.root.clone();
+        let dot = tree.to_dot_with(options, |key, _| key.to_string());
+        assert!(dot.contains("n1 -> lost1 [style=dashed, color=red];"));
+        left.borrow_mut().parent = tree.root.as_ref().map(Rc::clone);
+    }
+}
@@ -0,0 +1,392 @@
+use std::cell::RefCell;
+use std::cmp::{Ord, Ordering};
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::{Link, Mode, Tree, TreeNode};
+
+/// Error of `try_` methods of `Tree`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeError {
+    /// Node that had to be read or changed is borrowed from outside of tree.
+    Busy,
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::Busy => write!(f, "tree node is borrowed elsewhere"),
+        }
+    }
+}
+
+impl Error for TreeError {}
+
+type Node<K, V> = Rc<RefCell<TreeNode<K, V>>>;
+
+/// How operation uses nodes on its path.
+#[derive(Clone, Copy)]
+enum Access {
+    /// Path is only read.
+    Read,
+    /// Path changes, sizes are recounted from children.
+    Write,
+    /// Path changes and children get new parents.
+    Relink,
+}
+
+type Check<K, V> = fn(&Node<K, V>) -> Result<(), TreeError>;
+
+fn readable<K: Ord, V>(node: &Node<K, V>) -> Result<(), TreeError> {
+    node.try_borrow().map(drop).map_err(|_| TreeError::Busy)
+}
+
+fn writable<K: Ord, V>(node: &Node<K, V>) -> Result<(), TreeError> {
+    node.try_borrow_mut().map(drop).map_err(|_| TreeError::Busy)
+}
+
+/// Fallible twins of basic operations.
+/// They look at every node they may touch first, so tree is left as it was on error.
+impl<K: Ord, V> Tree<K, V> {
+    /// Inserts key-value like `insert`, but fails instead of panicking
+    /// if a node on the way is borrowed from outside.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<Option<V>, TreeError> {
+        // Splaying rotates nodes, so their children get new parents
+        let access = match self.mode {
+            Mode::Unbalanced => Access::Write,
+            Mode::Splay => Access::Relink,
+        };
+        self.check_path(&key, access)?;
+        Ok(self.insert(key, value))
+    }
+
+    /// Returns copy of value like `get`, but fails instead of panicking
+    /// if a node on the way is mutably borrowed from outside.
+    pub fn try_get(&self, key: &K) -> Result<Option<V>, TreeError>
+    where
+        V: Clone,
+    {
+        self.check_path(key, Access::Read)?;
+        Ok(self.get(key))
+    }
+
+    /// Deletes node like `delete`, but fails instead of panicking
+    /// if a node that changes is borrowed from outside.
+    pub fn try_remove(&mut self, key: &K) -> Result<Option<Node<K, V>>, TreeError> {
+        let node = match self.check_path(key, Access::Relink)? {
+            Some(node) if key.cmp(&node.borrow().key) == Ordering::Equal => node,
+            _ => return Ok(None),
+        };
+
+        // Node with both children is replaced by least node of its right subtree
+        let tree_node = node.borrow();
+        if let (Some(_), Some(right)) = (&tree_node.left, &tree_node.right) {
+            let mut current = Rc::clone(right);
+            loop {
+                let next = {
+                    writable(&current)?;
+                    let node = current.borrow();
+                    for child in node.left.iter().chain(node.right.iter()) {
+                        writable(child)?;
+                    }
+                    node.left.clone()
+                };
+                match next {
+                    Some(next) => current = next,
+                    None => break,
+                }
+            }
+        }
+        drop(tree_node);
+        Ok(self.delete(key))
+    }
+
+    /// Returns iterator over nodes in key order, which yields `TreeError::Busy`
+    /// instead of panicking when it can not step over a node. It ends after an error.
+    pub fn try_iter(&self) -> TryIter<'_, K, V> {
+        TryIter {
+            root: self.root.clone(),
+            current: None,
+            done: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Descends towards given key, checking that visited nodes
+    /// and their children can be borrowed as access needs.
+    /// Returns last visited node.
+    fn check_path(&self, key: &K, access: Access) -> Result<Link<K, V>, TreeError> {
+        let mut current = match &self.root {
+            None => return Ok(None),
+            Some(root) => Rc::clone(root),
+        };
+
+        loop {
+            let next = {
+                let node = match access {
+                    Access::Read => current.try_borrow(),
+                    _ => {
+                        writable(&current)?;
+                        current.try_borrow()
+                    }
+                };
+                let node = node.map_err(|_| TreeError::Busy)?;
+
+                // Copied node hands its children to the copy
+                let children = match access {
+                    Access::Read => None,
+                    Access::Write if !self.cow.is_shared(&node) => Some(readable as Check<K, V>),
+                    _ => Some(writable as Check<K, V>),
+                };
+                if let Some(check) = children {
+                    for child in node.left.iter().chain(node.right.iter()) {
+                        check(child)?;
+                    }
+                }
+                match key.cmp(&node.key) {
+                    Ordering::Less => node.left.clone(),
+                    Ordering::Greater => node.right.clone(),
+                    Ordering::Equal => None,
+                }
+            };
+
+            match next {
+                Some(next) => current = next,
+                None => return Ok(Some(current)),
+            }
+        }
+    }
+}
+
+/// Iterator over tree nodes that reports borrowed nodes, see `Tree::try_iter`.
+/// Next node is looked up only when asked for, so yielded nodes may be borrowed in between.
+pub struct TryIter<'a, K: Ord, V> {
+    /// Taken on first step.
+    root: Link<K, V>,
+    current: Link<K, V>,
+    done: bool,
+    marker: PhantomData<&'a Tree<K, V>>,
+}
+
+impl<'a, K: Ord, V> TryIter<'a, K, V> {
+    /// Same walk as `TreeNodeIterator::next_of`, with borrows that may fail.
+    fn step(&mut self) -> Result<Link<K, V>, TreeError> {
+        let current = match &self.current {
+            None => return try_least(self.root.take()),
+            Some(current) => Rc::clone(current),
+        };
+
+        let right = current
+            .try_borrow()
+            .map_err(|_| TreeError::Busy)?
+            .right
+            .clone();
+        if right.is_some() {
+            return try_least(right);
+        }
+
+        // Going up until we come from the left side
+        let mut this = current;
+        loop {
+            let this_node = this.try_borrow().map_err(|_| TreeError::Busy)?;
+            let parent = match &this_node.parent {
+                None => return Ok(None),
+                Some(parent) => Rc::clone(parent),
+            };
+            let parent_node = parent.try_borrow().map_err(|_| TreeError::Busy)?;
+            if this_node.key.cmp(&parent_node.key) == Ordering::Less {
+                drop(parent_node);
+                return Ok(Some(parent));
+            }
+            drop((this_node, parent_node));
+            this = parent;
+        }
+    }
+}
+
+/// Descends to least node of given subtree.
+fn try_least<K: Ord, V>(subtree: Link<K, V>) -> Result<Link<K, V>, TreeError> {
+    let mut current = match subtree {
+        None => return Ok(None),
+        Some(node) => node,
+    };
+    loop {
+        let left = current
+            .try_borrow()
+            .map_err(|_| TreeError::Busy)?
+            .left
+            .clone();
+        match left {
+            Some(left) => current = left,
+            None => return Ok(Some(current)),
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for TryIter<'a, K, V> {
+    type Item = Result<Node<K, V>, TreeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.step() {
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Ok(Some(node)) => {
+                self.current = Some(Rc::clone(&node));
+                Some(Ok(node))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TreeError;
+    use crate::tests::paper_tree;
+    use crate::{Mode, Tree};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn busy_instead_of_panic() {
+        for mode in &[Mode::Unbalanced, Mode::Splay] {
+            let mut tree = paper_tree(*mode);
+            let node = tree.find_node(&70).unwrap();
+            let held = node.borrow_mut();
+
+            assert_eq!(tree.try_get(&60), Err(TreeError::Busy));
+            assert_eq!(tree.try_get(&70), Err(TreeError::Busy));
+            assert_eq!(tree.try_insert(65, 65), Err(TreeError::Busy));
+            assert_eq!(
+                tree.try_remove(&99).map(|node| node.is_some()),
+                Err(TreeError::Busy)
+            );
+            assert_eq!(
+                tree.try_remove(&50).map(|node| node.is_some()),
+                Err(TreeError::Busy)
+            );
+
+            // Paths that do not cross held node still work
+            assert_eq!(tree.try_get(&10), Ok(Some(10)));
+            assert_eq!(tree.try_insert(400, 400), Ok(None));
+            assert_eq!(tree.try_remove(&300).map(|node| node.is_some()), Ok(true));
+            drop(held);
+
+            assert_eq!(tree.validate(), Ok(()));
+            assert_eq!(tree.len(), 9);
+            assert_eq!(tree.try_insert(65, 65), Ok(None));
+            assert_eq!(tree.try_remove(&50).map(|node| node.is_some()), Ok(true));
+            assert_eq!(tree.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn shared_borrow_blocks_changes_only() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let node = tree.find_node(&200).unwrap();
+        let held = node.borrow();
+        assert_eq!(tree.try_get(&300), Ok(Some(300)));
+        assert_eq!(tree.try_insert(250, 250), Err(TreeError::Busy));
+        assert_eq!(tree.try_insert(10, 11), Ok(Some(10)));
+        drop(held);
+    }
+
+    #[test]
+    fn try_iter() {
+        let tree = paper_tree(Mode::Unbalanced);
+        let keys: Result<Vec<i64>, TreeError> = tree
+            .try_iter()
+            .map(|node| node.map(|node| node.borrow().key))
+            .collect();
+        assert_eq!(keys, Ok(vec![10, 50, 60, 70, 99, 100, 115, 200, 300]));
+
+        // Yielded node may be borrowed until the next step
+        let mut iter = tree.try_iter();
+        let first = iter.next().unwrap().unwrap();
+        let held = first.borrow_mut();
+        assert_eq!(iter.next().map(|node| node.is_ok()), Some(false));
+        assert!(iter.next().is_none());
+        drop(held);
+
+        let node = tree.find_node(&99).unwrap();
+        let held = node.borrow_mut();
+        let keys: Vec<_> = tree
+            .try_iter()
+            .map(|node| node.map(|node| node.borrow().key))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![Ok(10), Ok(50), Ok(60), Ok(70), Err(TreeError::Busy)]
+        );
+        drop(held);
+        assert_eq!(Tree::<i64, i64>::new().try_iter().count(), 0);
+    }
+
+    #[test]
+    fn random_borrows_never_panic() {
+        let mut seed: u64 = 11;
+        let mut next = move || {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) as i64
+        };
+
+        for mode in &[Mode::Unbalanced, Mode::Splay] {
+            let mut tree = Tree::with_mode(*mode);
+            let mut model = BTreeMap::new();
+            let mut snapshot = None;
+            for _ in 0..2000 {
+                if next() % 50 == 0 {
+                    snapshot = Some(tree.snapshot());
+                }
+                let held = tree.find_node(&(next() % 64));
+                let key = next() % 64;
+
+                // Held borrow is dropped before model is compared
+                {
+                    let _shared;
+                    let _exclusive;
+                    match (&held, next() % 2) {
+                        (Some(node), 0) => _shared = node.borrow(),
+                        (Some(node), _) => _exclusive = node.borrow_mut(),
+                        (None, _) => {}
+                    }
+                    match next() % 3 {
+                        0 => {
+                            if let Ok(value) = tree.try_get(&key) {
+                                assert_eq!(value, model.get(&key).cloned());
+                            }
+                        }
+                        1 => {
+                            if let Ok(replaced) = tree.try_insert(key, key * 10) {
+                                assert_eq!(replaced, model.insert(key, key * 10));
+                            }
+                        }
+                        _ => {
+                            if let Ok(removed) = tree.try_remove(&key) {
+                                assert_eq!(removed.is_some(), model.remove(&key).is_some());
+                            }
+                        }
+                    }
+                    let _ = tree.try_iter().count();
+                }
+                assert_eq!(tree.validate(), Ok(()));
+            }
+            drop(snapshot);
+
+            let keys: Result<Vec<i64>, TreeError> = tree
+                .try_iter()
+                .map(|node| node.map(|node| node.borrow().key))
+                .collect();
+            assert_eq!(keys, Ok(model.keys().cloned().collect()));
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::cell::RefCell;
+use std::cmp::Ord;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+use crate::{Event, ObserverId, Tree};
+
+/// Owned copy of an `Event`, as kept in change feed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change<K, V> {
+    Inserted { key: K, value: V },
+    Replaced { key: K, old: V, new: V },
+    Removed { key: K, value: V },
+}
+
+impl<K: Clone, V: Clone> Change<K, V> {
+    pub(crate) fn of(event: &Event<'_, K, V>) -> Self {
+        match *event {
+            Event::Inserted { key, value } => Change::Inserted {
+                key: key.clone(),
+                value: value.clone(),
+            },
+            Event::Replaced { key, old, new } => Change::Replaced {
+                key: key.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            },
+            Event::Removed { key, value } => Change::Removed {
+                key: key.clone(),
+                value: value.clone(),
+            },
+        }
+    }
+}
+
+/// Consumer asked for changes that were already dropped from the log.
+/// It has to resync from the tree itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lagged {
+    /// Sequence number of the oldest change still in the log.
+    pub oldest: u64,
+}
+
+impl fmt::Display for Lagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "changes before {} were dropped from the feed",
+            self.oldest
+        )
+    }
+}
+
+impl Error for Lagged {}
+
+/// Change with its sequence number.
+type Entry<K, V> = (u64, Change<K, V>);
+
+/// Bounded log of changes, shared by tree observer and the feed.
+struct Log<K, V> {
+    /// Sequence number the next change gets.
+    next_seq: u64,
+    changes: VecDeque<Entry<K, V>>,
+    capacity: usize,
+}
+
+/// Pull-based feed of tree changes, see `Tree::change_feed`.
+pub struct ChangeFeed<K, V> {
+    log: Rc<RefCell<Log<K, V>>>,
+    id: ObserverId,
+}
+
+impl<K: Ord + Clone + 'static, V: Clone + 'static> Tree<K, V> {
+    /// Starts logging every change of tree, keeping last `capacity` of them.
+    /// Changes are numbered from 1 on, in order they happen.
+    /// Feed works as an observer, `unobserve(feed.observer_id())` stops it.
+    ///
+    /// Panics if capacity is zero.
+    pub fn change_feed(&mut self, capacity: usize) -> ChangeFeed<K, V> {
+        assert!(capacity > 0, "Change feed capacity must not be zero");
+        let log = Rc::new(RefCell::new(Log {
+            next_seq: 1,
+            changes: VecDeque::with_capacity(capacity),
+            capacity,
+        }));
+
+        let sink: Weak<RefCell<Log<K, V>>> = Rc::downgrade(&log);
+        let id = self.observe_while(move |event| match sink.upgrade() {
+            Some(log) => {
+                log.borrow_mut().push(Change::of(event));
+                true
+            }
+            // Feed is dropped, observer goes away with it
+            None => false,
+        });
+        ChangeFeed { log, id }
+    }
+}
+
+impl<K, V> Log<K, V> {
+    fn push(&mut self, change: Change<K, V>) {
+        if self.changes.len() == self.capacity {
+            self.changes.pop_front();
+        }
+        self.changes.push_back((self.next_seq, change));
+        self.next_seq += 1;
+    }
+}
+
+impl<K: Clone, V: Clone> ChangeFeed<K, V> {
+    /// Returns copies of changes made after the one with given sequence number, oldest first.
+    /// `changes_since(0)` returns everything since feed started.
+    /// Fails if some of those changes were already dropped from the log.
+    pub fn changes_since(&self, seq: u64) -> Result<Vec<Entry<K, V>>, Lagged> {
+        let log = self.log.borrow();
+        if seq >= log.next_seq - 1 {
+            return Ok(vec![]);
+        }
+        let oldest = log.next_seq - log.changes.len() as u64;
+        if seq + 1 < oldest {
+            return Err(Lagged { oldest });
+        }
+
+        let skip = (seq + 1 - oldest) as usize;
+        Ok(log.changes.iter().skip(skip).cloned().collect())
+    }
+
+    /// Returns sequence number of the last change, 0 if there were none.
+    pub fn last_seq(&self) -> u64 {
+        self.log.borrow().next_seq - 1
+    }
+
+    /// Returns id of observer that fills the feed.
+    pub fn observer_id(&self) -> ObserverId {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, Lagged};
+    use crate::Tree;
+
+    #[test]
+    fn catch_up() {
+        let mut tree = Tree::new();
+        tree.insert(0, 0);
+        let feed = tree.change_feed(10);
+        assert_eq!(feed.last_seq(), 0);
+        assert_eq!(feed.changes_since(0), Ok(vec![]));
+
+        tree.insert(1, 10);
+        tree.insert(1, 11);
+        tree.delete(&0);
+        assert_eq!(feed.last_seq(), 3);
+        assert_eq!(
+            feed.changes_since(0),
+            Ok(vec![
+                (1, Change::Inserted { key: 1, value: 10 }),
+                (
+                    2,
+                    Change::Replaced {
+                        key: 1,
+                        old: 10,
+                        new: 11
+                    }
+                ),
+                (3, Change::Removed { key: 0, value: 0 }),
+            ])
+        );
+        assert_eq!(feed.changes_since(2).unwrap().len(), 1);
+        assert_eq!(feed.changes_since(3), Ok(vec![]));
+        assert_eq!(feed.changes_since(7), Ok(vec![]));
+        assert_eq!(feed.changes_since(u64::MAX), Ok(vec![]));
+    }
+
+    #[test]
+    fn lagged() {
+        let mut tree = Tree::new();
+        let feed = tree.change_feed(4);
+        for i in 0..10 {
+            tree.insert(i, i);
+        }
+
+        assert_eq!(feed.changes_since(0), Err(Lagged { oldest: 7 }));
+        assert_eq!(feed.changes_since(5), Err(Lagged { oldest: 7 }));
+        let changes = feed.changes_since(6).unwrap();
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[0], (7, Change::Inserted { key: 6, value: 6 }));
+        assert_eq!(changes[3].0, 10);
+
+        tree.clear();
+        assert_eq!(feed.last_seq(), 20);
+        assert_eq!(feed.changes_since(15), Err(Lagged { oldest: 17 }));
+    }
+
+    #[test]
+    fn stops() {
+        let mut tree = Tree::new();
+        let feed = tree.change_feed(4);
+        tree.insert(1, 1);
+        assert!(tree.unobserve(feed.observer_id()));
+        tree.insert(2, 2);
+        assert_eq!(feed.last_seq(), 1);
+
+        // Dropped feed takes its observer away on next change
+        for _ in 0..3 {
+            drop(tree.change_feed(1));
+        }
+        assert_eq!(tree.observers.list.len(), 3);
+        tree.insert(3, 3);
+        assert_eq!(tree.len(), 3);
+        assert!(tree.observers.list.is_empty());
+    }
+}
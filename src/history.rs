@@ -0,0 +1,329 @@
+use std::cmp::Ord;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+use crate::Tree;
+
+/// Change that can be applied to tree.
+/// Log keeps changes that bring tree back to its previous state.
+enum Change<K, V> {
+    Insert(K, V),
+    Delete(K),
+}
+
+/// Logged change with id of operation it belongs to.
+/// Id stays the same while change travels between undo and redo logs.
+struct Entry<K, V> {
+    id: u64,
+    change: Change<K, V>,
+}
+
+/// `Tree` that remembers its changes and can undo and redo them.
+/// Every `insert` and `delete` records its inverse change, up to configured depth.
+pub struct UndoTree<K: Ord + Clone, V: Clone> {
+    tree: Tree<K, V>,
+    /// Inverse changes of applied operations, latest at the back.
+    undo_log: VecDeque<Entry<K, V>>,
+    /// Inverse changes of undone operations, latest at the back.
+    redo_log: Vec<Entry<K, V>>,
+    /// Maximum number of operations that can be undone.
+    depth: usize,
+    /// Id of newest operation forgotten because of depth, 0 if none.
+    /// State right after it is still reachable, it is where undo log starts.
+    forgotten: u64,
+    /// Id of last new operation.
+    last_id: u64,
+}
+
+/// State of `UndoTree` to get back to with `rollback_to`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Savepoint(u64);
+
+/// Savepoint can not be reached anymore: its operations fell out of history,
+/// or were undone and replaced by new ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SavepointError;
+
+impl fmt::Display for SavepointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "savepoint is not in history anymore")
+    }
+}
+
+impl Error for SavepointError {}
+
+impl<K: Ord + Clone, V: Clone> UndoTree<K, V> {
+    /// Creates empty tree with unlimited history.
+    pub fn new() -> Self {
+        Self::with_depth(usize::MAX)
+    }
+
+    /// Creates empty tree that remembers up to `depth` last operations.
+    pub fn with_depth(depth: usize) -> Self {
+        UndoTree {
+            tree: Tree::new(),
+            undo_log: VecDeque::new(),
+            redo_log: vec![],
+            depth,
+            forgotten: 0,
+            last_id: 0,
+        }
+    }
+
+    /// Returns underlying tree for reading.
+    pub fn tree(&self) -> &Tree<K, V> {
+        &self.tree
+    }
+
+    /// Returns number of nodes in tree.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Checks if tree has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Returns copy of value stored by given key.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.tree.get(key)
+    }
+
+    /// Inserts key-value into tree.
+    /// Returns optional value of replaced value, if there was any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let replaced = self.tree.insert(key.clone(), value);
+        let inverse = match &replaced {
+            Some(old) => Change::Insert(key, old.clone()),
+            None => Change::Delete(key),
+        };
+        self.record(inverse);
+        replaced
+    }
+
+    /// Tries to delete node with given key.
+    /// Returns value of deleted node if there was any.
+    pub fn delete(&mut self, key: &K) -> Option<V> {
+        let value = self.tree.remove_value(key)?;
+        self.record(Change::Insert(key.clone(), value.clone()));
+        Some(value)
+    }
+
+    /// Returns savepoint of current state.
+    pub fn savepoint(&self) -> Savepoint {
+        Savepoint(self.undo_log.back().map_or(self.forgotten, |entry| entry.id))
+    }
+
+    /// Undoes operations until tree is back to the savepoint.
+    /// Undone operations can be redone. Nothing is undone if savepoint can not be reached.
+    pub fn rollback_to(&mut self, savepoint: Savepoint) -> Result<(), SavepointError> {
+        let Savepoint(id) = savepoint;
+        let keep = if id == self.forgotten {
+            0
+        } else {
+            match self.undo_log.iter().rposition(|entry| entry.id == id) {
+                Some(position) => position + 1,
+                None => return Err(SavepointError),
+            }
+        };
+
+        while self.undo_log.len() > keep {
+            self.undo();
+        }
+        Ok(())
+    }
+
+    /// Undoes last operation.
+    /// Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo_log.pop_back() {
+            None => false,
+            Some(entry) => {
+                let change = self.apply(entry.change);
+                self.redo_log.push(Entry {
+                    id: entry.id,
+                    change,
+                });
+                true
+            }
+        }
+    }
+
+    /// Redoes last undone operation.
+    /// Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo_log.pop() {
+            None => false,
+            Some(entry) => {
+                let change = self.apply(entry.change);
+                self.push_undo(Entry {
+                    id: entry.id,
+                    change,
+                });
+                true
+            }
+        }
+    }
+
+    /// Applies change to tree and returns its inverse.
+    fn apply(&mut self, change: Change<K, V>) -> Change<K, V> {
+        match change {
+            Change::Insert(key, value) => match self.tree.insert(key.clone(), value) {
+                Some(old) => Change::Insert(key, old),
+                None => Change::Delete(key),
+            },
+            Change::Delete(key) => {
+                // Log always deletes a key that is there
+                let value = self.tree.remove_value(&key).unwrap();
+                Change::Insert(key, value)
+            }
+        }
+    }
+
+    /// Logs inverse of new operation, forgetting whatever was undone.
+    fn record(&mut self, change: Change<K, V>) {
+        self.redo_log.clear();
+        self.last_id += 1;
+        let id = self.last_id;
+        self.push_undo(Entry { id, change });
+    }
+
+    fn push_undo(&mut self, entry: Entry<K, V>) {
+        self.undo_log.push_back(entry);
+        if self.undo_log.len() > self.depth {
+            self.forgotten = self.undo_log.pop_front().unwrap().id;
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Default for UndoTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SavepointError, UndoTree};
+
+    fn entries(tree: &UndoTree<i64, i64>) -> Vec<(i64, i64)> {
+        let mut entries = vec![];
+        let mut cursor = tree.tree().cursor_front();
+        while let Some(node) = cursor.node() {
+            entries.push((node.borrow().key, node.borrow().value));
+            cursor.move_next();
+        }
+        entries
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut tree = UndoTree::new();
+        tree.insert(1, 1);
+        tree.insert(2, 2);
+        assert_eq!(tree.insert(1, 10), Some(1));
+        assert_eq!(tree.delete(&2), Some(2));
+        assert_eq!(tree.delete(&2), None);
+        assert_eq!(entries(&tree), vec![(1, 10)]);
+
+        assert!(tree.undo());
+        assert_eq!(entries(&tree), vec![(1, 10), (2, 2)]);
+        assert!(tree.undo());
+        assert_eq!(entries(&tree), vec![(1, 1), (2, 2)]);
+
+        assert!(tree.redo());
+        assert_eq!(entries(&tree), vec![(1, 10), (2, 2)]);
+
+        assert!(tree.undo());
+        assert!(tree.undo());
+        assert!(tree.undo());
+        assert!(!tree.undo());
+        assert!(tree.is_empty());
+
+        assert!(tree.redo());
+        assert!(tree.redo());
+        assert!(tree.redo());
+        assert!(tree.redo());
+        assert!(!tree.redo());
+        assert_eq!(entries(&tree), vec![(1, 10)]);
+    }
+
+    #[test]
+    fn node_held_outside() {
+        let mut tree = UndoTree::new();
+        tree.insert(1, 1);
+        tree.insert(2, 2);
+        let held = tree.tree().find_node(&2).unwrap();
+        assert_eq!(tree.delete(&2), Some(2));
+        assert!(tree.undo());
+        let again = tree.tree().find_node(&2).unwrap();
+        assert!(tree.undo());
+        assert!(tree.redo());
+        assert_eq!(entries(&tree), vec![(1, 1), (2, 2)]);
+        assert_eq!(held.borrow().value, 2);
+        assert_eq!(again.borrow().value, 2);
+    }
+
+    #[test]
+    fn new_operation_drops_redo() {
+        let mut tree = UndoTree::new();
+        tree.insert(1, 1);
+        tree.undo();
+        tree.insert(2, 2);
+        assert!(!tree.redo());
+        assert_eq!(entries(&tree), vec![(2, 2)]);
+    }
+
+    #[test]
+    fn savepoints() {
+        let mut tree = UndoTree::new();
+        let empty = tree.savepoint();
+        tree.insert(1, 1);
+        tree.insert(2, 2);
+        let two = tree.savepoint();
+        tree.insert(3, 3);
+        tree.delete(&1);
+        tree.insert(2, 20);
+
+        assert_eq!(tree.rollback_to(two), Ok(()));
+        assert_eq!(entries(&tree), vec![(1, 1), (2, 2)]);
+
+        assert_eq!(tree.rollback_to(empty), Ok(()));
+        assert!(tree.is_empty());
+
+        // Rolled back operations can be redone, savepoint is valid again
+        tree.redo();
+        tree.redo();
+        assert_eq!(tree.savepoint(), two);
+        tree.redo();
+        assert_eq!(entries(&tree), vec![(1, 1), (2, 2), (3, 3)]);
+
+        // Replaced history makes savepoint unreachable
+        tree.rollback_to(empty).unwrap();
+        tree.insert(5, 5);
+        assert_eq!(tree.rollback_to(two), Err(SavepointError));
+        assert_eq!(entries(&tree), vec![(5, 5)]);
+    }
+
+    #[test]
+    fn limited_depth() {
+        let mut tree = UndoTree::with_depth(2);
+        let empty = tree.savepoint();
+        tree.insert(1, 1);
+        let one = tree.savepoint();
+        tree.insert(2, 2);
+        tree.insert(3, 3);
+
+        assert_eq!(tree.rollback_to(empty), Err(SavepointError));
+        assert_eq!(tree.rollback_to(one), Ok(()));
+        assert_eq!(entries(&tree), vec![(1, 1)]);
+        assert!(!tree.undo());
+
+        let mut forgetful = UndoTree::with_depth(0);
+        forgetful.insert(1, 1);
+        assert!(!forgetful.undo());
+        assert_eq!(forgetful.len(), 1);
+    }
+}
@@ -1,8 +1,58 @@
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
 use std::cmp::{Ord, Ordering};
 
+mod batch;
+mod binary;
+mod cursor;
+mod dot;
+mod fallible;
+mod feed;
+mod history;
+mod mvcc;
+mod node_ref;
+mod observer;
+mod optimistic;
+mod order_check;
+mod persistent;
+mod pretty;
+mod retain;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod shape;
+mod snapshot;
+mod splay;
+mod split;
+mod stats;
+mod sync_tree;
+mod transaction;
+mod validate;
+mod watch;
+
+pub use batch::WriteBatch;
+pub use binary::{Decode, Encode, FormatError};
+pub use cursor::{Cursor, CursorMut, UnorderedKeyError};
+pub use dot::DotOptions;
+pub use fallible::{TreeError, TryIter};
+pub use feed::{Change, ChangeFeed, Lagged};
+pub use history::{Savepoint, SavepointError, UndoTree};
+pub use mvcc::{MvccTree, RangeAt};
+pub use node_ref::{Ancestors, NodeRef};
+pub use observer::{Event, ObserverId};
+pub use optimistic::{Conflict, OptimisticTransaction};
+pub use order_check::OrderError;
+pub use persistent::{PersistentIter, PersistentTree};
+pub use pretty::Pretty;
+pub use retain::ExtractIf;
+#[cfg(feature = "serde")]
+pub use serde_impl::DuplicateKeys;
+pub use snapshot::{SnapshotIter, TreeSnapshot};
+pub use stats::TreeStats;
+pub use sync_tree::SyncTree;
+pub use transaction::Transaction;
+pub use validate::InvariantError;
+
 /// My Little Tree implementation
 /// This tree is binary, bidirctional, unbalanced, based on Rc<RefCell<...>> combination.
 pub struct Tree<K: Ord, V> {
@@ -12,6 +62,27 @@ pub struct Tree<K: Ord, V> {
 
     /// Root element of Tree.
     root: Option<Rc<RefCell<TreeNode<K, V>>>>,
+
+    /// How tree reshapes itself on access.
+    mode: Mode,
+
+    /// Bookkeeping of nodes shared with snapshots.
+    cow: snapshot::CowState<K, V>,
+
+    /// Callbacks reported every change.
+    observers: observer::Observers<K, V>,
+
+    /// Whether descents double-check what `Ord` says, see `Tree::set_order_checks`.
+    check_order: bool,
+}
+
+/// Strategy of keeping tree in shape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Nodes stay where they were inserted. Good old stupid tree.
+    Unbalanced,
+    /// Accessed nodes are moved to the root, so hot keys are cheap to reach.
+    Splay,
 }
 
 /// This is node of My Little Tree.
@@ -19,18 +90,32 @@ pub struct Tree<K: Ord, V> {
 pub struct TreeNode<K: Ord, V> {
     key: K,
     value: V,
+    /// Number of nodes in subtree, this node included.
+    count: usize,
+    /// Generation of tree this node was created or copied in.
+    /// Node is shared with snapshots if it is older than its tree.
+    generation: u64,
+    /// Copy of this node that replaced it in tree, if it was shared and then changed.
+    moved: Option<Weak<RefCell<TreeNode<K, V>>>>,
     parent: Option<Rc<RefCell<TreeNode<K, V>>>>,
     right: Option<Rc<RefCell<TreeNode<K, V>>>>,
     left: Option<Rc<RefCell<TreeNode<K, V>>>>,
 }
 
+/// Optional reference to a node, as stored in parent and child fields.
+type Link<K, V> = Option<Rc<RefCell<TreeNode<K, V>>>>;
+
 /// Some utilities and recusive funtions.
 impl<K: Ord, V> TreeNode<K, V> {
     /// Returns node with given key-value pair and no references.
+    #[allow(clippy::redundant_field_names)]
     fn new(key: K, value: V) -> Self {
         TreeNode {
             key: key,
             value: value,
+            count: 1,
+            generation: 0,
+            moved: None,
             parent: None,
             left: None,
             right: None,
@@ -55,6 +140,40 @@ impl<K: Ord, V> TreeNode<K, V> {
         Rc::clone(self.parent.as_ref().unwrap())
     }
 
+    /// Unwraps detached node into its key-value pair.
+    fn into_entry(node: Rc<RefCell<TreeNode<K, V>>>) -> (K, V) {
+        match Rc::try_unwrap(node) {
+            Ok(node) => {
+                let node = node.into_inner();
+                (node.key, node.value)
+            }
+            Err(_) => panic!("Node is referenced from outside of tree"),
+        }
+    }
+
+    /// Cuts old references of node deleted from tree and moves its value out.
+    /// Node may still be held by handles from outside, its value is copied then.
+    fn take_value(node: Rc<RefCell<TreeNode<K, V>>>) -> V
+    where
+        V: Clone,
+    {
+        {
+            let mut node = node.borrow_mut();
+            node.parent = None;
+            node.left = None;
+            node.right = None;
+        }
+        match Rc::try_unwrap(node) {
+            Ok(node) => node.into_inner().value,
+            Err(node) => node.borrow().value.clone(),
+        }
+    }
+
+    /// Recomputes subtree size from children.
+    fn recount(&mut self) {
+        self.count = 1 + count_of(&self.left) + count_of(&self.right);
+    }
+
     /// Recusive search for node by given key.
     /// It is convinient to keep reference to itself as argument.
     fn find_node_r(
@@ -83,66 +202,169 @@ impl<K: Ord, V> TreeNode<K, V> {
             Some(lq) => lq.borrow().least_node_r(lq),
         }
     }
+
+    /// Recursive search for node with greatest key.
+    fn greatest_node_r(&self, myself: &Rc<RefCell<TreeNode<K, V>>>) -> Rc<RefCell<TreeNode<K, V>>> {
+        match &self.right {
+            None => Rc::clone(myself),
+            Some(lq) => lq.borrow().greatest_node_r(lq),
+        }
+    }
+}
+
+/// Returns size of subtree under given link.
+fn count_of<K: Ord, V>(link: &Link<K, V>) -> usize {
+    match link {
+        None => 0,
+        Some(node) => node.borrow().count,
+    }
 }
 
 impl<K: Ord, V> Tree<K, V> {
     /// Creates empty tree.
     pub fn new() -> Self {
+        Self::with_mode(Mode::Unbalanced)
+    }
+
+    /// Creates empty tree with given mode.
+    pub fn with_mode(mode: Mode) -> Self {
         Tree {
             size: 0,
             root: None,
+            mode,
+            cow: snapshot::CowState::new(),
+            observers: observer::Observers::new(),
+            check_order: false,
         }
     }
 
+    /// Returns mode this tree was created with.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
     /// Inserts key-value into tree.
     /// Returns optional value of replaced value, if there was any.
+    /// In splay mode inserted node becomes root.
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
-        let new_node = TreeNode::new(key, value);
+        let place = self
+            .place_of(&key)
+            .unwrap_or_else(|violation| panic!("{}", violation.describe()));
+        self.insert_at(place, TreeNode::new(key, value))
+    }
 
-        match &self.root {
-            None => {
-                self.root = Some(Rc::new(RefCell::new(new_node)));
-                self.size += 1;
-                return None;
+    /// Hangs new node at place found for its key, then splays it and reports the change.
+    fn insert_at(&mut self, place: Link<K, V>, new_node: TreeNode<K, V>) -> Option<V> {
+        let (node, replaced) = self.inner_insert(place, new_node);
+
+        if self.mode == Mode::Splay {
+            self.splay(&node);
+        }
+
+        match &replaced {
+            None => self.notify_inserted(&node),
+            Some(old) => {
+                let node = node.borrow();
+                self.observers.notify(&Event::Replaced {
+                    key: &node.key,
+                    old,
+                    new: &node.value,
+                });
             }
-            Some(link) => self.inner_insert(new_node, Rc::clone(&link)),
         }
+        self.finish_change();
+        replaced
     }
 
+    /// Hangs new node under the last node on its path, or replaces value of existing node.
+    /// Returns node that holds inserted key and replaced value.
     fn inner_insert(
         &mut self,
-        mut new_node: TreeNode<K, V>,
-        parent_ref: Rc<RefCell<TreeNode<K, V>>>,
-    ) -> Option<V> {
-        let parent = &mut parent_ref.borrow_mut();
-        match new_node.key.cmp(&parent.key) {
-            Ordering::Less => match &parent.left {
-                None => {
-                    new_node.parent = Some(Rc::clone(&parent_ref));
-                    let link = Rc::new(RefCell::new(new_node));
-                    parent.left = Some(link);
-                    self.size += 1;
-                    None
-                }
-                Some(link) => self.inner_insert(new_node, Rc::clone(link)),
-            },
-            Ordering::Greater => match &parent.right {
-                None => {
-                    new_node.parent = Some(Rc::clone(&parent_ref));
-                    let link = Rc::new(RefCell::new(new_node));
-                    parent.right = Some(link);
-                    self.size += 1;
-                    None
+        place: Link<K, V>,
+        new_node: TreeNode<K, V>,
+    ) -> (Rc<RefCell<TreeNode<K, V>>>, Option<V>) {
+        let place = match place {
+            None => return (self.attach(None, new_node, false), None),
+            Some(place) => self.own(&place),
+        };
+
+        let order = new_node.key.cmp(&place.borrow().key);
+        match order {
+            Ordering::Less => (self.attach(Some(place), new_node, true), None),
+            Ordering::Greater => (self.attach(Some(place), new_node, false), None),
+            Ordering::Equal => {
+                let replaced = std::mem::replace(&mut place.borrow_mut().value, new_node.value);
+                (place, Some(replaced))
+            }
+        }
+    }
+
+    /// Descends to node with given key.
+    /// Returns last node on the path if there is no such key.
+    fn find_node_or_last(&self, f: &K) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
+        let mut current = Rc::clone(self.root.as_ref()?);
+
+        loop {
+            let next = {
+                let node = current.borrow();
+                match f.cmp(&node.key) {
+                    Ordering::Less => node.left.clone(),
+                    Ordering::Greater => node.right.clone(),
+                    Ordering::Equal => None,
                 }
-                Some(link) => self.inner_insert(new_node, Rc::clone(link)),
-            },
-            Ordering::Equal => Some(std::mem::replace(&mut parent.value, new_node.value)),
+            };
+
+            match next {
+                Some(next) => current = next,
+                None => return Some(current),
+            }
         }
     }
 
-    /// Clears map by replacing with new tree.
+    /// Hangs new node as left or right child of given parent, or as root if there is no parent.
+    /// Chosen place must be free and keep the order.
+    fn attach(
+        &mut self,
+        parent: Link<K, V>,
+        mut new_node: TreeNode<K, V>,
+        to_left: bool,
+    ) -> Rc<RefCell<TreeNode<K, V>>> {
+        new_node.generation = self.cow.generation();
+        let link = Rc::new(RefCell::new(new_node));
+        self.hang(parent, Rc::clone(&link), to_left);
+        link
+    }
+
+    /// Hangs detached subtree as left or right child of given parent, or as root if there is no parent.
+    /// Chosen place must be free and keep the order.
+    fn hang(&mut self, parent: Link<K, V>, subtree: Rc<RefCell<TreeNode<K, V>>>, to_left: bool) {
+        let parent = parent.map(|parent| self.own(&parent));
+        subtree.borrow_mut().parent = parent.clone();
+        let count = subtree.borrow().count;
+
+        match &parent {
+            None => self.root = Some(subtree),
+            Some(parent) if to_left => parent.borrow_mut().left = Some(subtree),
+            Some(parent) => parent.borrow_mut().right = Some(subtree),
+        }
+
+        self.recount_up(parent);
+        self.size += count;
+    }
+
+    /// Clears map, mode and observers are kept.
     pub fn clear(&mut self) {
-        *self = Self::new();
+        let root = self.root.take();
+        self.size = 0;
+
+        if !self.observers.is_empty() {
+            let mut current = root.as_ref().map(|root| root.borrow().least_node_r(root));
+            while let Some(node) = current {
+                self.notify_removed(&node);
+                current = TreeNodeIterator::next_of(&node);
+            }
+            self.finish_change();
+        }
     }
 
     /// Returns number of nodes in tree.
@@ -163,16 +385,53 @@ impl<K: Ord, V> Tree<K, V> {
         }
     }
 
+    /// Takes tree apart into key-value pairs in key order.
+    /// Nodes must not be referenced from outside of tree.
+    pub(crate) fn into_entries(self) -> Vec<(K, V)> {
+        let nodes: Vec<_> = self.iter_node().collect();
+
+        // Parent links make cycles, so all links are cut before unwrapping
+        for node in nodes.iter() {
+            let mut node = node.borrow_mut();
+            node.parent = None;
+            node.left = None;
+            node.right = None;
+        }
+        nodes.into_iter().map(TreeNode::into_entry).collect()
+    }
+
+    /// Deletes node with given key and returns its value.
+    /// Value is copied if node is still held from outside.
+    pub(crate) fn remove_value(&mut self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        let node = self.delete(key)?;
+        Some(TreeNode::take_value(node))
+    }
+
     /// Tries to find node by given key.
+    /// Never reshapes tree, even in splay mode. See `splay_find_node` for that.
+    /// Changes made through returned node are seen by snapshots, see `snapshot`.
     pub fn find_node(&self, f: &K) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
-        if self.root.is_none() {
-            return None;
+        if self.check_order {
+            return self
+                .place_of(f)
+                .unwrap_or_else(|violation| panic!("{}", violation.describe()))
+                .filter(|node| f.cmp(&node.borrow().key) == Ordering::Equal);
         }
-
-        let root = self.root.as_ref().unwrap();
+        let root = self.root.as_ref()?;
         root.borrow().find_node_r(Rc::clone(root), f)
     }
 
+    /// Returns copy of value stored by given key.
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.find_node(key).map(|node| node.borrow().value.clone())
+    }
+
     /// Returns node with least key in tree.
     pub fn least_node(&self) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
         match &self.root {
@@ -191,177 +450,228 @@ impl<K: Ord, V> Tree<K, V> {
         }
     }
 
+    /// Returns node with greatest key in tree.
+    pub fn greatest_node(&self) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
+        let root = self.root.as_ref()?;
+        Some(root.borrow().greatest_node_r(root))
+    }
+
+    /// Closes public change of tree, once tree is consistent again.
+    /// Resumes panic of an observer, if there was one.
+    fn finish_change(&mut self) {
+        self.cow.end_change();
+        self.observers.finish();
+    }
+
+    /// Recomputes subtree sizes from given node up to the root.
+    fn recount_up(&self, node: Option<Rc<RefCell<TreeNode<K, V>>>>) {
+        let mut current = node;
+        while let Some(node) = current {
+            node.borrow_mut().recount();
+            current = node.borrow().parent.clone();
+        }
+    }
+
     /// Tries to delete node with given key.
     /// Returns deleted node if there was any.
     pub fn delete(&mut self, key: &K) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
-        let node = self.find_node(key);
-        match node {
-            None => None,
-            Some(ref node) => {
-                let tree_node = node.borrow();
-
-                let parent = &tree_node.parent;
-                if parent.is_none() {
-                    // deleting root
-                    match (tree_node.left.is_some(), tree_node.right.is_some()) {
-                        (false, false) => {
-                            self.root = None;
-                        }
-                        (true, false) => {
-                            let new_root = tree_node.left_sure();
-                            new_root.borrow_mut().parent = None;
-                            self.root = Some(Rc::clone(&new_root));
-                        }
-                        (false, true) => {
-                            let new_root = tree_node.right_sure();
-                            new_root.borrow_mut().parent = None;
-                            self.root = Some(Rc::clone(&new_root));
-                        }
-                        // Both exists, lets be lazy and go once right then full left
-                        // (pretty sure this will disbalance tree even more, but no one cares)
-                        (true, true) => {
-                            let shift_ref = tree_node.left.as_ref().unwrap();
-                            let mut shift = shift_ref.borrow_mut();
-
-                            let new_root_ref = tree_node.right_sure();
-                            let mut new_root = new_root_ref.borrow_mut();
-
-                            let new_parent_ref = new_root.least_node_r(&new_root_ref);
-
-                            // Now things are getting complicated
-                            // It is possible, that new_parent is the same as new_root
-                            // and we can not borrow it twice
-                            if Rc::ptr_eq(&new_root_ref, &new_parent_ref) {
-                                self.root = Some(Rc::clone(&new_root_ref));
-                                new_root.parent = None;
-
-                                shift.parent = Some(Rc::clone(&new_parent_ref));
-                                new_root.left = Some(Rc::clone(shift_ref));
-                            } else {
-                                let mut new_parent = new_parent_ref.borrow_mut();
+        let node = self.find_node(key)?;
+        let node = self.unlink(&node);
+        self.notify_removed(&node);
+        self.finish_change();
+        Some(node)
+    }
 
-                                self.root = Some(Rc::clone(&new_root_ref));
-                                new_root.parent = None;
+    /// Detaches node from tree, relinking its children in place.
+    /// Node itself keeps its old references.
+    /// Returns detached node, which is a copy of given one if it was shared with snapshots.
+    fn unlink(&mut self, node: &Rc<RefCell<TreeNode<K, V>>>) -> Rc<RefCell<TreeNode<K, V>>> {
+        // Everything that changes must be owned before we start borrowing
+        let node = self.own(node);
+        let right = node.borrow().right.clone();
+        let has_left = node.borrow().left.is_some();
+        if let (Some(right), true) = (right, has_left) {
+            let least = right.borrow().least_node_r(&right);
+            self.own(&least);
+        }
 
-                                shift.parent = Some(Rc::clone(&new_parent_ref));
-                                new_parent.left = Some(Rc::clone(shift_ref));
-                            }
-                        }
-                    }
-                } else {
-                    let parent_ref = tree_node.parent_sure();
-                    let mut parent = parent_ref.borrow_mut();
-
-                    // messy but i still like it
-                    match (
-                        tree_node.left.is_some(),
-                        tree_node.right.is_some(),
-                        tree_node.key.cmp(&parent.key),
-                    ) {
-                        (_, _, Ordering::Equal) => {
-                            unreachable!(
-                                "Parent has same key with its child. Should not not have happened"
-                            );
-                        }
-                        (false, false, Ordering::Less) => {
-                            parent.left = None;
-                        }
-                        (false, false, Ordering::Greater) => {
-                            parent.right = None;
-                        }
-                        (true, false, Ordering::Less) => {
-                            let shift_ref = tree_node.left.as_ref().unwrap();
-                            let mut shift = shift_ref.borrow_mut();
+        let tree_node = node.borrow();
 
-                            shift.parent = Some(Rc::clone(&parent_ref));
-                            parent.left = Some(Rc::clone(shift_ref));
-                        }
-                        (true, false, Ordering::Greater) => {
-                            let shift_ref = tree_node.left.as_ref().unwrap();
-                            let mut shift = shift_ref.borrow_mut();
+        // Lowest node whose subtree changes, sizes are fixed from it upwards
+        let recount_from = match &tree_node.right {
+            Some(right) if tree_node.left.is_some() => Some(right.borrow().least_node_r(right)),
+            _ => tree_node.parent.clone(),
+        };
 
-                            shift.parent = Some(Rc::clone(&parent_ref));
-                            parent.right = Some(Rc::clone(shift_ref));
-                        }
-                        (false, true, Ordering::Less) => {
-                            let shift_ref = tree_node.right.as_ref().unwrap();
-                            let mut shift = shift_ref.borrow_mut();
+        let parent = &tree_node.parent;
+        if parent.is_none() {
+            // deleting root
+            match (tree_node.left.is_some(), tree_node.right.is_some()) {
+                (false, false) => {
+                    self.root = None;
+                }
+                (true, false) => {
+                    let new_root = tree_node.left_sure();
+                    new_root.borrow_mut().parent = None;
+                    self.root = Some(Rc::clone(&new_root));
+                }
+                (false, true) => {
+                    let new_root = tree_node.right_sure();
+                    new_root.borrow_mut().parent = None;
+                    self.root = Some(Rc::clone(&new_root));
+                }
+                // Both exists, lets be lazy and go once right then full left
+                // (pretty sure this will disbalance tree even more, but no one cares)
+                (true, true) => {
+                    let shift_ref = tree_node.left.as_ref().unwrap();
+                    let mut shift = shift_ref.borrow_mut();
+
+                    let new_root_ref = tree_node.right_sure();
+                    let mut new_root = new_root_ref.borrow_mut();
+
+                    let new_parent_ref = new_root.least_node_r(&new_root_ref);
+
+                    // Now things are getting complicated
+                    // It is possible, that new_parent is the same as new_root
+                    // and we can not borrow it twice
+                    if Rc::ptr_eq(&new_root_ref, &new_parent_ref) {
+                        self.root = Some(Rc::clone(&new_root_ref));
+                        new_root.parent = None;
+
+                        shift.parent = Some(Rc::clone(&new_parent_ref));
+                        new_root.left = Some(Rc::clone(shift_ref));
+                    } else {
+                        let mut new_parent = new_parent_ref.borrow_mut();
+
+                        self.root = Some(Rc::clone(&new_root_ref));
+                        new_root.parent = None;
+
+                        shift.parent = Some(Rc::clone(&new_parent_ref));
+                        new_parent.left = Some(Rc::clone(shift_ref));
+                    }
+                }
+            }
+        } else {
+            let parent_ref = tree_node.parent_sure();
+            let mut parent = parent_ref.borrow_mut();
+
+            // messy but i still like it
+            match (
+                tree_node.left.is_some(),
+                tree_node.right.is_some(),
+                tree_node.key.cmp(&parent.key),
+            ) {
+                (_, _, Ordering::Equal) => {
+                    unreachable!(
+                        "Parent has same key with its child. Should not not have happened"
+                    );
+                }
+                (false, false, Ordering::Less) => {
+                    parent.left = None;
+                }
+                (false, false, Ordering::Greater) => {
+                    parent.right = None;
+                }
+                (true, false, Ordering::Less) => {
+                    let shift_ref = tree_node.left.as_ref().unwrap();
+                    let mut shift = shift_ref.borrow_mut();
 
-                            shift.parent = Some(Rc::clone(&parent_ref));
-                            parent.left = Some(Rc::clone(shift_ref));
-                        }
-                        (false, true, Ordering::Greater) => {
-                            let shift_ref = tree_node.right.as_ref().unwrap();
-                            let mut shift = shift_ref.borrow_mut();
+                    shift.parent = Some(Rc::clone(&parent_ref));
+                    parent.left = Some(Rc::clone(shift_ref));
+                }
+                (true, false, Ordering::Greater) => {
+                    let shift_ref = tree_node.left.as_ref().unwrap();
+                    let mut shift = shift_ref.borrow_mut();
 
-                            shift.parent = Some(Rc::clone(&parent_ref));
-                            parent.right = Some(Rc::clone(shift_ref));
-                        }
-                        // Both exists, lets be lazy and go once right then full left
-                        // (pretty sure this will disbalance tree even more, but no one cares)
-                        (true, true, Ordering::Less) => {
-                            let shift_ref = &tree_node.left_sure();
-                            let mut shift = shift_ref.borrow_mut();
-
-                            let shifter_ref = tree_node.right_sure();
-                            let mut shifter = shifter_ref.borrow_mut();
-
-                            let new_parent_ref = shifter.least_node_r(&shifter_ref);
-
-                            // Now things are getting complicated
-                            // It is possible, that new_parent is the same as new_root
-                            // and we can not borrow it twice
-                            if Rc::ptr_eq(&shifter_ref, &new_parent_ref) {
-                                shifter.left = Some(Rc::clone(shift_ref));
-                                shift.parent = Some(Rc::clone(&new_parent_ref));
-
-                                parent.left = Some(Rc::clone(&shifter_ref));
-                                shifter.parent = Some(Rc::clone(&parent_ref));
-                            } else {
-                                let mut new_parent = new_parent_ref.borrow_mut();
+                    shift.parent = Some(Rc::clone(&parent_ref));
+                    parent.right = Some(Rc::clone(shift_ref));
+                }
+                (false, true, Ordering::Less) => {
+                    let shift_ref = tree_node.right.as_ref().unwrap();
+                    let mut shift = shift_ref.borrow_mut();
 
-                                new_parent.left = Some(Rc::clone(shift_ref));
-                                shift.parent = Some(Rc::clone(&new_parent_ref));
+                    shift.parent = Some(Rc::clone(&parent_ref));
+                    parent.left = Some(Rc::clone(shift_ref));
+                }
+                (false, true, Ordering::Greater) => {
+                    let shift_ref = tree_node.right.as_ref().unwrap();
+                    let mut shift = shift_ref.borrow_mut();
 
-                                parent.left = Some(Rc::clone(&shifter_ref));
-                                shifter.parent = Some(Rc::clone(&parent_ref));
-                            }
-                        }
-                        (true, true, Ordering::Greater) => {
-                            let shift_ref = &tree_node.left_sure();
-                            let mut shift = shift_ref.borrow_mut();
+                    shift.parent = Some(Rc::clone(&parent_ref));
+                    parent.right = Some(Rc::clone(shift_ref));
+                }
+                // Both exists, lets be lazy and go once right then full left
+                // (pretty sure this will disbalance tree even more, but no one cares)
+                (true, true, Ordering::Less) => {
+                    let shift_ref = &tree_node.left_sure();
+                    let mut shift = shift_ref.borrow_mut();
+
+                    let shifter_ref = tree_node.right_sure();
+                    let mut shifter = shifter_ref.borrow_mut();
+
+                    let new_parent_ref = shifter.least_node_r(&shifter_ref);
+
+                    // Now things are getting complicated
+                    // It is possible, that new_parent is the same as new_root
+                    // and we can not borrow it twice
+                    if Rc::ptr_eq(&shifter_ref, &new_parent_ref) {
+                        shifter.left = Some(Rc::clone(shift_ref));
+                        shift.parent = Some(Rc::clone(&new_parent_ref));
+
+                        parent.left = Some(Rc::clone(&shifter_ref));
+                        shifter.parent = Some(Rc::clone(&parent_ref));
+                    } else {
+                        let mut new_parent = new_parent_ref.borrow_mut();
+
+                        new_parent.left = Some(Rc::clone(shift_ref));
+                        shift.parent = Some(Rc::clone(&new_parent_ref));
+
+                        parent.left = Some(Rc::clone(&shifter_ref));
+                        shifter.parent = Some(Rc::clone(&parent_ref));
+                    }
+                }
+                (true, true, Ordering::Greater) => {
+                    let shift_ref = &tree_node.left_sure();
+                    let mut shift = shift_ref.borrow_mut();
 
-                            let shifter_ref = tree_node.right_sure();
-                            let mut shifter = shifter_ref.borrow_mut();
+                    let shifter_ref = tree_node.right_sure();
+                    let mut shifter = shifter_ref.borrow_mut();
 
-                            let new_parent_ref = shifter.least_node_r(&shifter_ref);
+                    let new_parent_ref = shifter.least_node_r(&shifter_ref);
 
-                            // Now things are getting complicated
-                            // It is possible, that new_parent is the same as new_root
-                            // and we can not borrow it twice
-                            if Rc::ptr_eq(&shifter_ref, &new_parent_ref) {
-                                shifter.left = Some(Rc::clone(shift_ref));
-                                shift.parent = Some(Rc::clone(&new_parent_ref));
+                    // Now things are getting complicated
+                    // It is possible, that new_parent is the same as new_root
+                    // and we can not borrow it twice
+                    if Rc::ptr_eq(&shifter_ref, &new_parent_ref) {
+                        shifter.left = Some(Rc::clone(shift_ref));
+                        shift.parent = Some(Rc::clone(&new_parent_ref));
 
-                                parent.right = Some(Rc::clone(&shifter_ref));
-                                shifter.parent = Some(Rc::clone(&parent_ref));
-                            } else {
-                                let mut new_parent = new_parent_ref.borrow_mut();
+                        parent.right = Some(Rc::clone(&shifter_ref));
+                        shifter.parent = Some(Rc::clone(&parent_ref));
+                    } else {
+                        let mut new_parent = new_parent_ref.borrow_mut();
 
-                                new_parent.left = Some(Rc::clone(shift_ref));
-                                shift.parent = Some(Rc::clone(&new_parent_ref));
+                        new_parent.left = Some(Rc::clone(shift_ref));
+                        shift.parent = Some(Rc::clone(&new_parent_ref));
 
-                                parent.right = Some(Rc::clone(&shifter_ref));
-                                shifter.parent = Some(Rc::clone(&parent_ref));
-                            }
-                        }
+                        parent.right = Some(Rc::clone(&shifter_ref));
+                        shifter.parent = Some(Rc::clone(&parent_ref));
                     }
                 }
-
-                self.size -= 1;
-                Some(Rc::clone(node))
             }
         }
+
+        self.recount_up(recount_from);
+        self.size -= 1;
+
+        drop(tree_node);
+        node
+    }
+}
+
+impl<K: Ord, V> Default for Tree<K, V> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -379,20 +689,23 @@ pub struct TreeNodeIterator<K: Ord, V> {
 impl<K: Ord, V> TreeNodeIterator<K, V> {
     fn find_next(&mut self) {
         // this was called so current_node is not None
+        let node = self.current_node.take().unwrap();
+        self.current_node = Self::next_of(&node);
+    }
 
-        // Copy of current node
-        let ref_curr = Rc::clone(self.current_node.as_ref().unwrap());
+    /// Returns node with next key after given one, if there is any.
+    pub(crate) fn next_of(ref_curr: &Rc<RefCell<TreeNode<K, V>>>) -> Link<K, V> {
         let curr = ref_curr.borrow();
 
         // Check if we have right child
         match &curr.right {
             Some(lq) => {
                 // If so, get lowest from right child
-                self.current_node = Some(lq.borrow().least_node_r(lq));
+                Some(lq.borrow().least_node_r(lq))
             }
             None => {
                 // Otherwise we are going up
-                let mut this = Rc::clone(&ref_curr);
+                let mut this = Rc::clone(ref_curr);
 
                 loop {
                     // borrow checker, please
@@ -402,8 +715,7 @@ impl<K: Ord, V> TreeNodeIterator<K, V> {
                     match &parent {
                         None => {
                             // If there was no parent then there are no other nodes in tree
-                            std::mem::swap(&mut self.current_node, &mut None);
-                            break;
+                            return None;
                         }
                         Some(ref p) => {
                             // If there was a parent node then we have to check if this relation is left or right
@@ -413,8 +725,38 @@ impl<K: Ord, V> TreeNodeIterator<K, V> {
                                 continue;
                             } else {
                                 // If this is left child relation then parent is next node
-                                std::mem::swap(&mut self.current_node, &mut Some(Rc::clone(p)));
-                                break;
+                                return Some(Rc::clone(p));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns node with previous key before given one, if there is any.
+    /// Mirrored `next_of`.
+    pub(crate) fn prev_of(ref_curr: &Rc<RefCell<TreeNode<K, V>>>) -> Link<K, V> {
+        let curr = ref_curr.borrow();
+
+        match &curr.left {
+            Some(lq) => Some(lq.borrow().greatest_node_r(lq)),
+            None => {
+                // Going up until we come from the right side
+                let mut this = Rc::clone(ref_curr);
+
+                loop {
+                    let cp = Rc::clone(&this);
+                    let parent = &cp.borrow().parent;
+
+                    match &parent {
+                        None => return None,
+                        Some(ref p) => {
+                            if this.borrow().key.cmp(&p.borrow().key) == Ordering::Less {
+                                this = Rc::clone(p);
+                                continue;
+                            } else {
+                                return Some(Rc::clone(p));
                             }
                         }
                     }
@@ -455,9 +797,29 @@ impl<K: Ord, V> IntoIterator for Tree<K, V> {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
+    /// Keys of the tree from the paper, in order of insertion.
+    pub(crate) const PAPER: [i64; 9] = [100, 50, 10, 70, 60, 99, 200, 115, 300];
+
+    /// Tree from the paper with values equal to keys.
+    /// It is built unbalanced, so its shape is the same in every mode.
+    pub(crate) fn paper_tree(mode: Mode) -> Tree<i64, i64> {
+        let mut tree = Tree::new();
+        for key in PAPER.iter() {
+            tree.insert(*key, *key);
+        }
+        tree.mode = mode;
+        tree
+    }
+
+    /// Keys of tree in order.
+    pub(crate) fn keys(tree: Tree<i64, i64>) -> Vec<i64> {
+        tree.into_iter().map(|n| n.borrow().key).collect()
+    }
+
     #[test]
     fn empty_tree() {
         let tree: Tree<i64, i64> = Tree::new();
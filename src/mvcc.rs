@@ -0,0 +1,299 @@
+use std::cmp::Ord;
+use std::ops::{Bound, RangeBounds};
+
+use crate::{Cursor, Tree};
+
+/// Value history of one key: commit version and value, None marks deletion.
+/// Oldest first.
+type VersionChain<V> = Vec<(u64, Option<V>)>;
+
+/// Multi-version layer over `Tree`.
+/// Every write is stamped with next commit version, and tree can be read as of any version.
+/// Version 0 is the empty tree before first write.
+pub struct MvccTree<K: Ord, V> {
+    tree: Tree<K, VersionChain<V>>,
+    /// Last commit version.
+    version: u64,
+    /// Versions before this one were forgotten by `gc_before`.
+    gc_floor: u64,
+}
+
+impl<K: Ord, V> MvccTree<K, V> {
+    /// Creates empty tree.
+    pub fn new() -> Self {
+        MvccTree {
+            tree: Tree::new(),
+            version: 0,
+            gc_floor: 0,
+        }
+    }
+
+    /// Returns last commit version.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns oldest version that can still be read, see `gc_before`.
+    pub fn gc_floor(&self) -> u64 {
+        self.gc_floor
+    }
+
+    /// Writes new value of key.
+    /// Returns commit version of this write.
+    pub fn insert(&mut self, key: K, value: V) -> u64 {
+        self.version += 1;
+        let version = self.version;
+
+        let mut cursor = self.tree.lower_bound_mut(&key);
+        if cursor.key().is_some_and(|k| *k == key) {
+            cursor.value_mut().unwrap().push((version, Some(value)));
+        } else {
+            // Cursor is at the first greater key, so new one fits right before it
+            cursor
+                .insert_before(key, vec![(version, Some(value))])
+                .expect("lower bound keeps the order");
+        }
+        version
+    }
+
+    /// Marks key as deleted.
+    /// Returns commit version of deletion, or None if there was no such key.
+    pub fn delete(&mut self, key: &K) -> Option<u64> {
+        let mut cursor = self.tree.lower_bound_mut(key);
+        if cursor.key().map_or(true, |k| *k != *key) {
+            return None;
+        }
+        if let Some((_, None)) = cursor.value().unwrap().last() {
+            return None;
+        }
+
+        self.version += 1;
+        cursor.value_mut().unwrap().push((self.version, None));
+        Some(self.version)
+    }
+
+    /// Forgets versions older than given one, versions past the last commit count as the last one.
+    /// Reads as of this version and later stay exact, earlier reads panic.
+    pub fn gc_before(&mut self, version: u64) {
+        let version = version.min(self.version);
+        if version <= self.gc_floor {
+            return;
+        }
+        self.gc_floor = version;
+        self.tree.retain(|_, chain| {
+            // Latest write visible at the version is still needed, everything before is not
+            let visible = chain.iter().rposition(|(v, _)| *v <= version);
+            if let Some(visible) = visible {
+                chain.drain(..visible);
+                if chain.len() == 1 && chain[0].1.is_none() {
+                    chain.clear();
+                }
+            }
+            !chain.is_empty()
+        });
+    }
+}
+
+impl<K: Ord, V: Clone> MvccTree<K, V> {
+    /// Returns copy of latest value of key.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.get_at(key, self.version)
+    }
+
+    /// Returns copy of value key had as of given version.
+    ///
+    /// Panics if version was forgotten by `gc_before`.
+    pub fn get_at(&self, key: &K, version: u64) -> Option<V> {
+        self.check_version(version);
+        let node = self.tree.find_node(key)?;
+        let node = node.borrow();
+        visible_at(&node.value, version).cloned()
+    }
+
+    /// Returns iterator over copies of entries in given range as of given version.
+    ///
+    /// Panics if version was forgotten by `gc_before`.
+    pub fn range_at<R>(&self, range: R, version: u64) -> RangeAt<'_, K, V>
+    where
+        K: Clone,
+        R: RangeBounds<K>,
+    {
+        self.check_version(version);
+        let cursor = match range.start_bound() {
+            Bound::Included(start) => self.tree.lower_bound(start),
+            Bound::Excluded(start) => self.tree.upper_bound(start),
+            Bound::Unbounded => self.tree.cursor_front(),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => Bound::Included(end.clone()),
+            Bound::Excluded(end) => Bound::Excluded(end.clone()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        RangeAt {
+            cursor,
+            end,
+            version,
+            finished: false,
+        }
+    }
+}
+
+impl<K: Ord, V> MvccTree<K, V> {
+    fn check_version(&self, version: u64) {
+        assert!(
+            version >= self.gc_floor,
+            "Version {} was forgotten, versions before {} are collected",
+            version,
+            self.gc_floor
+        );
+    }
+}
+
+impl<K: Ord, V> Default for MvccTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns value of chain visible as of given version.
+fn visible_at<V>(chain: &[(u64, Option<V>)], version: u64) -> Option<&V> {
+    chain
+        .iter()
+        .rev()
+        .find(|(v, _)| *v <= version)
+        .and_then(|(_, value)| value.as_ref())
+}
+
+/// Iterator returned by `MvccTree::range_at`.
+pub struct RangeAt<'a, K: Ord, V> {
+    cursor: Cursor<'a, K, VersionChain<V>>,
+    end: Bound<K>,
+    version: u64,
+    /// Set once end bound is passed, cursor would wrap around otherwise.
+    finished: bool,
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iterator for RangeAt<'a, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let node = self.cursor.node()?;
+            self.cursor.move_next();
+
+            let node = node.borrow();
+            let in_range = match &self.end {
+                Bound::Included(end) => node.key <= *end,
+                Bound::Excluded(end) => node.key < *end,
+                Bound::Unbounded => true,
+            };
+            if !in_range {
+                self.finished = true;
+            } else if let Some(value) = visible_at(&node.value, self.version) {
+                return Some((node.key.clone(), value.clone()));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MvccTree;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn read_as_of() {
+        let mut tree = MvccTree::new();
+        let v1 = tree.insert(1, "a");
+        let v2 = tree.insert(2, "b");
+        let v3 = tree.insert(1, "c");
+        let v4 = tree.delete(&2).unwrap();
+
+        assert_eq!(tree.get_at(&1, 0), None);
+        assert_eq!(tree.get_at(&1, v1), Some("a"));
+        assert_eq!(tree.get_at(&1, v2), Some("a"));
+        assert_eq!(tree.get_at(&1, v3), Some("c"));
+        assert_eq!(tree.get_at(&2, v3), Some("b"));
+        assert_eq!(tree.get_at(&2, v4), None);
+        assert_eq!(tree.get(&1), Some("c"));
+        assert_eq!(tree.version(), v4);
+    }
+
+    #[test]
+    fn delete_missing() {
+        let mut tree: MvccTree<i64, i64> = MvccTree::new();
+        assert_eq!(tree.delete(&1), None);
+        tree.insert(1, 1);
+        assert!(tree.delete(&1).is_some());
+        assert_eq!(tree.delete(&1), None);
+        assert_eq!(tree.version(), 2);
+    }
+
+    #[test]
+    fn ranges() {
+        let mut tree = MvccTree::new();
+        for i in 0..10 {
+            tree.insert(i, i * 10);
+        }
+        let before = tree.version();
+        tree.delete(&3);
+        tree.insert(4, 400);
+        tree.insert(20, 200);
+
+        let old: Vec<(i64, i64)> = tree.range_at(2..5, before).collect();
+        assert_eq!(old, vec![(2, 20), (3, 30), (4, 40)]);
+
+        let new: Vec<(i64, i64)> = tree.range_at(2..=5, tree.version()).collect();
+        assert_eq!(new, vec![(2, 20), (4, 400), (5, 50)]);
+
+        assert_eq!(tree.range_at(.., before).count(), 10);
+        assert_eq!(tree.range_at(.., tree.version()).count(), 10);
+        assert_eq!(tree.range_at(8.., 5).count(), 0);
+        assert_eq!(tree.range_at(..2, 1).collect::<Vec<_>>(), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn gc_keeps_needed_versions() {
+        let mut tree = MvccTree::new();
+        let v1 = tree.insert(1, 1);
+        tree.insert(2, 2);
+        let v3 = tree.insert(1, 10);
+        let v4 = tree.delete(&2).unwrap();
+        tree.insert(1, 100);
+
+        tree.gc_before(v4);
+        assert_eq!(tree.gc_floor(), v4);
+        assert_eq!(tree.get_at(&1, v4), Some(10));
+        assert_eq!(tree.get(&1), Some(100));
+        assert_eq!(tree.get_at(&2, v4), None);
+
+        // Deleted key is gone completely, older versions are forgotten
+        assert_eq!(tree.tree.len(), 1);
+        assert_eq!(tree.tree.find_node(&1).unwrap().borrow().value.len(), 2);
+
+        // Forgotten versions can not be read, not even by going back with gc
+        tree.gc_before(v1);
+        assert_eq!(tree.gc_floor(), v4);
+        let read = |version| catch_unwind(AssertUnwindSafe(|| tree.get_at(&1, version)));
+        assert!(read(v1).is_err());
+        assert!(read(v3).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| tree.range_at(.., v3).count())).is_err());
+    }
+
+    #[test]
+    fn gc_past_last_version() {
+        let mut tree = MvccTree::new();
+        tree.insert(1, 1);
+        tree.insert(1, 2);
+        tree.gc_before(tree.version() + 5);
+        assert_eq!(tree.gc_floor(), tree.version());
+        assert_eq!(tree.get(&1), Some(2));
+
+        // Next writes are still readable as of their versions
+        let v3 = tree.insert(1, 3);
+        assert_eq!(tree.get_at(&1, v3), Some(3));
+        assert_eq!(tree.get_at(&1, v3 - 1), Some(2));
+    }
+}
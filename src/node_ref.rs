@@ -0,0 +1,175 @@
+use std::cell::{Ref, RefCell};
+use std::cmp::Ord;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::{Link, Tree, TreeNode};
+
+/// Read only handle to a node of tree, see `Tree::root_ref`.
+/// Tree can not change while handle lives, and key is never handed out mutably,
+/// so order of tree can not be broken through it.
+pub struct NodeRef<'a, K: Ord, V> {
+    node: Rc<RefCell<TreeNode<K, V>>>,
+    marker: PhantomData<&'a Tree<K, V>>,
+}
+
+impl<K: Ord, V> Tree<K, V> {
+    /// Returns handle to the root node.
+    pub fn root_ref(&self) -> Option<NodeRef<'_, K, V>> {
+        NodeRef::wrap(self.root.clone())
+    }
+
+    /// Returns handle to node with given key.
+    pub fn node_ref(&self, key: &K) -> Option<NodeRef<'_, K, V>> {
+        NodeRef::wrap(self.find_node(key))
+    }
+
+    /// Returns handle to node with least key.
+    pub fn least_ref(&self) -> Option<NodeRef<'_, K, V>> {
+        NodeRef::wrap(self.least_node())
+    }
+
+    /// Returns handle to node with greatest key.
+    pub fn greatest_ref(&self) -> Option<NodeRef<'_, K, V>> {
+        NodeRef::wrap(self.greatest_node())
+    }
+}
+
+impl<'a, K: Ord, V> NodeRef<'a, K, V> {
+    fn wrap(link: Link<K, V>) -> Option<Self> {
+        link.map(|node| NodeRef {
+            node,
+            marker: PhantomData,
+        })
+    }
+
+    /// Borrows key of node.
+    pub fn key(&self) -> Ref<'_, K> {
+        Ref::map(self.node.borrow(), |node| &node.key)
+    }
+
+    /// Borrows value of node.
+    pub fn value(&self) -> Ref<'_, V> {
+        Ref::map(self.node.borrow(), |node| &node.value)
+    }
+
+    /// Returns handle to parent node, None for root.
+    pub fn parent(&self) -> Option<NodeRef<'a, K, V>> {
+        Self::wrap(self.node.borrow().parent.clone())
+    }
+
+    /// Returns handle to left child, if there is one.
+    pub fn left(&self) -> Option<NodeRef<'a, K, V>> {
+        Self::wrap(self.node.borrow().left.clone())
+    }
+
+    /// Returns handle to right child, if there is one.
+    pub fn right(&self) -> Option<NodeRef<'a, K, V>> {
+        Self::wrap(self.node.borrow().right.clone())
+    }
+
+    /// Checks if node has no children.
+    pub fn is_leaf(&self) -> bool {
+        let node = self.node.borrow();
+        node.left.is_none() && node.right.is_none()
+    }
+
+    /// Number of nodes in subtree of this node, itself included.
+    pub fn subtree_len(&self) -> usize {
+        self.node.borrow().count
+    }
+
+    /// Distance from the root, which has depth 0.
+    pub fn depth(&self) -> usize {
+        self.ancestors().count()
+    }
+
+    /// Iterates over parent, grandparent and so on up to the root.
+    pub fn ancestors(&self) -> Ancestors<'a, K, V> {
+        Ancestors {
+            next: self.parent(),
+        }
+    }
+
+    /// Checks if both handles point to the same node.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.node, &other.node)
+    }
+}
+
+impl<'a, K: Ord, V> Clone for NodeRef<'a, K, V> {
+    fn clone(&self) -> Self {
+        NodeRef {
+            node: Rc::clone(&self.node),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Iterator over ancestors of node, see `NodeRef::ancestors`.
+pub struct Ancestors<'a, K: Ord, V> {
+    next: Option<NodeRef<'a, K, V>>,
+}
+
+impl<'a, K: Ord, V> Iterator for Ancestors<'a, K, V> {
+    type Item = NodeRef<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        self.next = current.parent();
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::paper_tree;
+    use crate::{Mode, Tree};
+
+    #[test]
+    fn navigation() {
+        let tree = paper_tree(Mode::Unbalanced);
+        let root = tree.root_ref().unwrap();
+        assert_eq!(*root.key(), 100);
+        assert_eq!(*root.value(), 100);
+        assert!(root.parent().is_none());
+        assert_eq!(root.depth(), 0);
+        assert_eq!(root.subtree_len(), 9);
+
+        let left = root.left().unwrap();
+        assert_eq!(*left.key(), 50);
+        assert!(left.parent().unwrap().ptr_eq(&root));
+        assert!(!left.is_leaf());
+        assert!(left.left().unwrap().is_leaf());
+        assert!(root.right().unwrap().left().unwrap().is_leaf());
+        assert!(left.left().unwrap().left().is_none());
+
+        assert_eq!(*tree.least_ref().unwrap().key(), 10);
+        assert_eq!(*tree.greatest_ref().unwrap().key(), 300);
+        assert!(tree.node_ref(&42).is_none());
+        assert!(Tree::<i64, i64>::new().root_ref().is_none());
+    }
+
+    #[test]
+    fn depth_and_ancestors() {
+        let tree = paper_tree(Mode::Unbalanced);
+        let node = tree.node_ref(&60).unwrap();
+        assert_eq!(node.depth(), 3);
+        let ancestors: Vec<i64> = node.ancestors().map(|node| *node.key()).collect();
+        assert_eq!(ancestors, vec![70, 50, 100]);
+        assert_eq!(tree.root_ref().unwrap().ancestors().count(), 0);
+    }
+
+    #[test]
+    fn borrows_are_shared() {
+        let tree = paper_tree(Mode::Unbalanced);
+        let node = tree.node_ref(&70).unwrap();
+        let key = node.key();
+        let value = node.value();
+
+        // Reading tree around borrowed node is fine
+        assert_eq!(tree.get(&99), Some(99));
+        assert_eq!(tree.node_ref(&70).unwrap().depth(), 2);
+        assert_eq!((*key, *value), (70, 70));
+    }
+}
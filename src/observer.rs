@@ -0,0 +1,265 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::cmp::Ord;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+use crate::{Tree, TreeNode, TreeNodeIterator};
+
+/// Change of tree reported to observers, see `Tree::observe`.
+#[derive(Debug)]
+pub enum Event<'a, K, V> {
+    /// New key was added.
+    Inserted { key: &'a K, value: &'a V },
+    /// Value of existing key was replaced.
+    Replaced { key: &'a K, old: &'a V, new: &'a V },
+    /// Key was removed, along with its value.
+    Removed { key: &'a K, value: &'a V },
+}
+
+/// Handle of registered observer, used to unregister it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+/// Returns false when it wants to be unregistered.
+type Observer<K, V> = Box<dyn FnMut(&Event<'_, K, V>) -> bool>;
+
+/// Observers registered on a tree.
+pub(crate) struct Observers<K, V> {
+    pub(crate) list: Vec<(ObserverId, Observer<K, V>)>,
+    next_id: u64,
+    /// First panic of an observer during current change, resumed once change is done.
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+impl<K, V> Observers<K, V> {
+    pub(crate) fn new() -> Self {
+        Observers {
+            list: vec![],
+            next_id: 0,
+            panic: None,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Calls every observer in order of registration.
+    /// Panicking observer is dropped, the rest still get the event.
+    pub(crate) fn notify(&mut self, event: &Event<'_, K, V>) {
+        let panic = &mut self.panic;
+        self.list.retain_mut(|(_, observer)| {
+            match panic::catch_unwind(AssertUnwindSafe(|| observer(event))) {
+                Ok(keep) => keep,
+                Err(payload) => {
+                    panic.get_or_insert(payload);
+                    false
+                }
+            }
+        });
+    }
+
+    /// Resumes panic of an observer, if there was one.
+    /// Called by every change of tree once tree is consistent again.
+    pub(crate) fn finish(&mut self) {
+        if let Some(payload) = self.panic.take() {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+impl<K: Ord, V> Tree<K, V> {
+    /// Registers observer called on every change of tree, in order changes happen.
+    /// Bulk changes report every key, in key order. Changes of values made in place,
+    /// through `CursorMut::value_mut` or `retain` predicate, are not reported.
+    ///
+    /// Observers are called in order of registration. Observer that panics is unregistered,
+    /// the rest of observers still get the event and the change of tree is completed;
+    /// then the panic is resumed.
+    pub fn observe<F>(&mut self, mut observer: F) -> ObserverId
+    where
+        F: FnMut(&Event<'_, K, V>) + 'static,
+    {
+        self.observe_while(move |event| {
+            observer(event);
+            true
+        })
+    }
+
+    /// Registers observer that stays until it returns false.
+    pub(crate) fn observe_while<F>(&mut self, observer: F) -> ObserverId
+    where
+        F: FnMut(&Event<'_, K, V>) -> bool + 'static,
+    {
+        let id = ObserverId(self.observers.next_id);
+        self.observers.next_id += 1;
+        self.observers.list.push((id, Box::new(observer)));
+        id
+    }
+
+    /// Unregisters observer. Returns false if there was no such observer.
+    pub fn unobserve(&mut self, id: ObserverId) -> bool {
+        let len = self.observers.list.len();
+        self.observers.list.retain(|(other, _)| *other != id);
+        self.observers.list.len() != len
+    }
+
+    /// Returns all nodes in key order, or nothing if there is nobody to report them to.
+    pub(crate) fn nodes_if(&self, observed: bool) -> Vec<Rc<RefCell<TreeNode<K, V>>>> {
+        let mut nodes = vec![];
+        let mut current = if observed { self.least_node() } else { None };
+        while let Some(node) = current {
+            current = TreeNodeIterator::next_of(&node);
+            nodes.push(node);
+        }
+        nodes
+    }
+
+    /// Reports node that was just inserted.
+    pub(crate) fn notify_inserted(&mut self, node: &Rc<RefCell<TreeNode<K, V>>>) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let node = node.borrow();
+        self.observers.notify(&Event::Inserted {
+            key: &node.key,
+            value: &node.value,
+        });
+    }
+
+    /// Reports node that was just removed.
+    pub(crate) fn notify_removed(&mut self, node: &Rc<RefCell<TreeNode<K, V>>>) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let node = node.borrow();
+        self.observers.notify(&Event::Removed {
+            key: &node.key,
+            value: &node.value,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Event;
+    use crate::{Mode, Tree, WriteBatch};
+    use std::cell::RefCell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    fn record(tree: &mut Tree<i64, i64>) -> Log {
+        let log = Log::default();
+        let sink = Rc::clone(&log);
+        tree.observe(move |event| {
+            let line = match event {
+                Event::Inserted { key, value } => format!("+{}={}", key, value),
+                Event::Replaced { key, old, new } => format!("~{}={}>{}", key, old, new),
+                Event::Removed { key, value } => format!("-{}={}", key, value),
+            };
+            sink.borrow_mut().push(line);
+        });
+        log
+    }
+
+    fn take(log: &Log) -> Vec<String> {
+        log.borrow_mut().drain(..).collect()
+    }
+
+    #[test]
+    fn single_changes() {
+        for mode in &[Mode::Unbalanced, Mode::Splay] {
+            let mut tree = Tree::with_mode(*mode);
+            let log = record(&mut tree);
+
+            tree.insert(2, 20);
+            tree.insert(1, 10);
+            tree.insert(2, 21);
+            tree.delete(&1);
+            tree.delete(&5);
+            assert_eq!(take(&log), vec!["+2=20", "+1=10", "~2=20>21", "-1=10"]);
+
+            tree.insert(3, 30);
+            tree.clear();
+            assert_eq!(take(&log), vec!["+3=30", "-2=21", "-3=30"]);
+        }
+    }
+
+    #[test]
+    fn bulk_changes() {
+        let mut tree = Tree::new();
+        for key in &[50, 20, 80, 10, 30] {
+            tree.insert(*key, *key);
+        }
+        let log = record(&mut tree);
+
+        tree.retain(|key, _| *key != 20 && *key != 80);
+        assert_eq!(take(&log), vec!["-20=20", "-80=80"]);
+
+        let mut other = tree.split_off(&30);
+        let other_log = record(&mut other);
+        assert_eq!(take(&log), vec!["-30=30", "-50=50"]);
+
+        tree.append(&mut other);
+        assert_eq!(take(&log), vec!["+30=30", "+50=50"]);
+        assert_eq!(take(&other_log), vec!["-30=30", "-50=50"]);
+
+        let mut batch = WriteBatch::new();
+        batch.put(40, 1);
+        batch.delete(10);
+        batch.put(10, 2);
+        batch.put(30, 3);
+        tree.apply_batch(batch);
+        assert_eq!(take(&log), vec!["-10=10", "+10=2", "~30=30>3", "+40=1"]);
+
+        let mut cursor = tree.lower_bound_mut(&30);
+        cursor.remove_current();
+        cursor.insert_before(35, 35).unwrap();
+        assert_eq!(take(&log), vec!["-30=3", "+35=35"]);
+    }
+
+    #[test]
+    fn unobserve() {
+        let mut tree = Tree::new();
+        let log = record(&mut tree);
+        let other = record(&mut tree);
+        let id = tree.observe(|_| {});
+
+        tree.insert(1, 1);
+        assert!(tree.unobserve(id));
+        assert!(!tree.unobserve(id));
+        tree.insert(2, 2);
+        assert_eq!(take(&log), take(&other));
+    }
+
+    #[test]
+    fn panicking_observer() {
+        let mut tree = Tree::new();
+        let before = record(&mut tree);
+        tree.observe(|event| {
+            if let Event::Removed { .. } = event {
+                panic!("observer failed");
+            }
+        });
+        let after = record(&mut tree);
+
+        tree.insert(1, 1);
+        tree.insert(2, 2);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| tree.clear()));
+        assert!(result.is_err());
+
+        // Change is completed, everybody else saw all of it
+        assert!(tree.is_empty());
+        let expected = vec!["+1=1", "+2=2", "-1=1", "-2=2"];
+        assert_eq!(take(&before), expected);
+        assert_eq!(take(&after), expected);
+
+        // Panicking observer is gone
+        tree.insert(3, 3);
+        tree.delete(&3);
+        assert_eq!(take(&after), vec!["+3=3", "-3=3"]);
+    }
+}
@@ -0,0 +1,401 @@
+use std::cmp::Ord;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+use crate::{SyncTree, Tree};
+
+/// Keys written by one commit.
+enum Written<K> {
+    Keys(Vec<K>),
+    /// Plain write through `SyncTree` methods, keys are not known.
+    Unknown,
+}
+
+/// Log of recent commits into `SyncTree`, used to validate optimistic transactions.
+/// Commit is kept while there is a running transaction started before it.
+pub(crate) struct CommitLog<K> {
+    /// Version of last commit.
+    version: u64,
+    /// Commits newer than start of oldest running transaction, oldest first.
+    commits: VecDeque<(u64, Written<K>)>,
+    /// Start versions of running transactions.
+    running: Vec<u64>,
+}
+
+impl<K: Ord> CommitLog<K> {
+    pub(crate) fn new() -> Self {
+        CommitLog {
+            version: 0,
+            commits: VecDeque::new(),
+            running: vec![],
+        }
+    }
+
+    /// Records write done outside of transactions.
+    pub(crate) fn record_untracked(&mut self) {
+        self.record(Written::Unknown);
+    }
+
+    fn record(&mut self, written: Written<K>) {
+        self.version += 1;
+        if !self.running.is_empty() {
+            self.commits.push_back((self.version, written));
+        }
+    }
+
+    fn start(&mut self) -> u64 {
+        self.running.push(self.version);
+        self.version
+    }
+
+    /// Forgets running transaction and commits nobody can conflict with anymore.
+    fn finish(&mut self, start: u64) {
+        if let Some(position) = self.running.iter().position(|s| *s == start) {
+            self.running.swap_remove(position);
+        }
+
+        match self.running.iter().min() {
+            None => self.commits.clear(),
+            Some(oldest) => {
+                while self.commits.front().is_some_and(|(v, _)| v <= oldest) {
+                    self.commits.pop_front();
+                }
+            }
+        }
+    }
+}
+
+/// Transaction failed validation: some key it read was written by a concurrent commit.
+/// Nothing was applied, start new transaction and retry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conflict;
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transaction read keys written by concurrent commit")
+    }
+}
+
+impl Error for Conflict {}
+
+impl<K: Ord + Clone, V: Clone> SyncTree<K, V> {
+    /// Starts optimistic transaction over shared tree.
+    /// Tree is not locked while transaction runs, conflicts are found on `commit`.
+    pub fn optimistic(&self) -> OptimisticTransaction<'_, K, V> {
+        let start = self.inner.write().unwrap().commits.start();
+        OptimisticTransaction {
+            tree: self,
+            start,
+            read_keys: vec![],
+            read_ranges: vec![],
+            pending: Tree::new(),
+            finished: false,
+        }
+    }
+}
+
+/// Optimistic transaction, see `SyncTree::optimistic`.
+/// Reads go to shared tree and are remembered, writes are buffered until commit.
+/// Dropping transaction without commit discards its writes.
+pub struct OptimisticTransaction<'a, K: Ord + Clone, V: Clone> {
+    tree: &'a SyncTree<K, V>,
+    /// Commit version transaction started at.
+    start: u64,
+    read_keys: Vec<K>,
+    read_ranges: Vec<(Bound<K>, Bound<K>)>,
+    /// Buffered writes, None marks deletion.
+    pending: Tree<K, Option<V>>,
+    /// Set by commit, so drop does not finish transaction twice.
+    finished: bool,
+}
+
+impl<'a, K: Ord + Clone, V: Clone> OptimisticTransaction<'a, K, V> {
+    /// Returns copy of value, taking own writes into account.
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        if let Some(node) = self.pending.find_node(key) {
+            return node.borrow().value.clone();
+        }
+
+        self.read_keys.push(key.clone());
+        self.tree.get(key)
+    }
+
+    /// Returns copies of entries in range, taking own writes into account.
+    pub fn range<R>(&mut self, range: R) -> Vec<(K, V)>
+    where
+        R: RangeBounds<K>,
+    {
+        let range = (
+            clone_bound(range.start_bound()),
+            clone_bound(range.end_bound()),
+        );
+        let committed = self.tree.range(range.clone());
+
+        // Own writes in range, from pending tree
+        let mut own = vec![];
+        let mut cursor = match &range.0 {
+            Bound::Included(start) => self.pending.lower_bound(start),
+            Bound::Excluded(start) => self.pending.upper_bound(start),
+            Bound::Unbounded => self.pending.cursor_front(),
+        };
+        while let Some(node) = cursor.node() {
+            let node = node.borrow();
+            if !range.contains(&node.key) {
+                break;
+            }
+            own.push((node.key.clone(), node.value.clone()));
+            cursor.move_next();
+        }
+
+        self.read_ranges.push(range);
+        merge(committed, own)
+    }
+
+    /// Buffers insertion of key-value.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.pending.insert(key, Some(value));
+    }
+
+    /// Buffers deletion of key.
+    pub fn delete(&mut self, key: &K) {
+        self.pending.insert(key.clone(), None);
+    }
+
+    /// Validates reads against commits made since start and applies writes.
+    /// Returns `Conflict` and applies nothing if any read key was written concurrently.
+    pub fn commit(mut self) -> Result<(), Conflict> {
+        let inner = &mut *self.tree.inner.write().unwrap();
+        let conflict = inner
+            .commits
+            .commits
+            .iter()
+            .filter(|(version, _)| *version > self.start)
+            .any(|(_, written)| self.overlaps(written));
+        // Commits validated against are still logged, forget them only now
+        inner.commits.finish(self.start);
+        self.finished = true;
+        if conflict {
+            return Err(Conflict);
+        }
+
+        let pending = std::mem::take(&mut self.pending);
+        let mut written = vec![];
+        for (key, value) in pending.into_entries() {
+            match value {
+                Some(value) => {
+                    inner.insert(key.clone(), value);
+                }
+                None => {
+                    inner.delete(&key);
+                }
+            }
+            written.push(key);
+        }
+
+        if !written.is_empty() {
+            inner.commits.record(Written::Keys(written));
+        }
+        Ok(())
+    }
+
+    /// Checks if commit wrote anything transaction has read.
+    fn overlaps(&self, written: &Written<K>) -> bool {
+        match written {
+            Written::Unknown => !self.read_keys.is_empty() || !self.read_ranges.is_empty(),
+            Written::Keys(keys) => keys.iter().any(|key| {
+                self.read_keys.contains(key) || self.read_ranges.iter().any(|r| r.contains(key))
+            }),
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Drop for OptimisticTransaction<'a, K, V> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        // Poisoned lock means tree is broken anyway, panicking here would abort
+        if let Ok(mut inner) = self.tree.inner.write() {
+            inner.commits.finish(self.start);
+        }
+    }
+}
+
+fn clone_bound<K: Clone>(bound: Bound<&K>) -> Bound<K> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.clone()),
+        Bound::Excluded(key) => Bound::Excluded(key.clone()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Merges committed entries with own writes, own writes win.
+fn merge<K: Ord, V>(committed: Vec<(K, V)>, own: Vec<(K, Option<V>)>) -> Vec<(K, V)> {
+    let mut merged = vec![];
+    let mut committed = committed.into_iter().peekable();
+
+    for (key, value) in own {
+        while committed.peek().is_some_and(|(k, _)| *k < key) {
+            merged.push(committed.next().unwrap());
+        }
+        if committed.peek().is_some_and(|(k, _)| *k == key) {
+            committed.next();
+        }
+        if let Some(value) = value {
+            merged.push((key, value));
+        }
+    }
+    merged.extend(committed);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Conflict;
+    use crate::SyncTree;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn reads_see_own_writes() {
+        let tree = SyncTree::new();
+        for i in 0..10 {
+            tree.insert(i, i);
+        }
+
+        let mut tx = tree.optimistic();
+        tx.insert(3, 30);
+        tx.insert(20, 20);
+        tx.delete(&4);
+
+        assert_eq!(tx.get(&3), Some(30));
+        assert_eq!(tx.get(&4), None);
+        assert_eq!(tx.get(&5), Some(5));
+        assert_eq!(tx.range(2..6), vec![(2, 2), (3, 30), (5, 5)]);
+        assert_eq!(tx.range(8..).len(), 3);
+        assert_eq!(tree.get(&3), Some(3));
+
+        tx.commit().unwrap();
+        assert_eq!(tree.get(&3), Some(30));
+        assert_eq!(tree.get(&4), None);
+        assert_eq!(tree.len(), 10);
+    }
+
+    #[test]
+    fn key_conflict() {
+        let tree = SyncTree::new();
+        tree.insert(1, 1);
+
+        let mut first = tree.optimistic();
+        let mut second = tree.optimistic();
+        let value = first.get(&1).unwrap();
+        first.insert(1, value + 1);
+        let value = second.get(&1).unwrap();
+        second.insert(1, value + 1);
+
+        assert_eq!(first.commit(), Ok(()));
+        assert_eq!(second.commit(), Err(Conflict));
+        assert_eq!(tree.get(&1), Some(2));
+    }
+
+    #[test]
+    fn range_conflict() {
+        let tree = SyncTree::new();
+        tree.insert(1, 1);
+        tree.insert(10, 10);
+
+        let mut reader = tree.optimistic();
+        let mut writer = tree.optimistic();
+        assert_eq!(reader.range(0..5).len(), 1);
+        reader.insert(100, 100);
+        writer.insert(3, 3);
+
+        writer.commit().unwrap();
+        assert_eq!(reader.commit(), Err(Conflict));
+        assert_eq!(tree.get(&100), None);
+    }
+
+    #[test]
+    fn no_conflict_outside_reads() {
+        let tree = SyncTree::new();
+        tree.insert(1, 1);
+
+        let mut first = tree.optimistic();
+        let mut second = tree.optimistic();
+        first.get(&1);
+        first.range(..0);
+        first.insert(2, 2);
+        second.insert(5, 5);
+        second.delete(&1);
+
+        second.commit().unwrap();
+        assert_eq!(first.commit(), Err(Conflict));
+
+        let mut third = tree.optimistic();
+        let mut fourth = tree.optimistic();
+        third.get(&7);
+        fourth.insert(8, 8);
+        fourth.commit().unwrap();
+        third.commit().unwrap();
+        assert_eq!(tree.range(..), vec![(5, 5), (8, 8)]);
+    }
+
+    #[test]
+    fn plain_writes_conflict() {
+        let tree = SyncTree::new();
+        let mut tx = tree.optimistic();
+        tx.get(&1);
+        tree.insert(2, 2);
+        assert_eq!(tx.commit(), Err(Conflict));
+
+        // Blind writes have nothing to conflict with
+        let mut tx = tree.optimistic();
+        tx.insert(3, 3);
+        tree.insert(4, 4);
+        assert_eq!(tx.commit(), Ok(()));
+    }
+
+    #[test]
+    fn log_is_trimmed() {
+        let tree = SyncTree::new();
+        {
+            let mut tx = tree.optimistic();
+            tx.insert(1, 1);
+            tree.insert(2, 2);
+        }
+        tree.insert(3, 3);
+        assert!(tree.inner.read().unwrap().commits.commits.is_empty());
+        assert!(tree.inner.read().unwrap().commits.running.is_empty());
+    }
+
+    #[test]
+    fn concurrent_counter() {
+        let tree = Arc::new(SyncTree::new());
+        tree.insert("counter", 0u64);
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let tree = Arc::clone(&tree);
+                thread::spawn(move || {
+                    for _ in 0..200 {
+                        loop {
+                            let mut tx = tree.optimistic();
+                            let value = tx.get(&"counter").unwrap();
+                            tx.insert("counter", value + 1);
+                            if tx.commit().is_ok() {
+                                break;
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(tree.get(&"counter"), Some(1600));
+    }
+}
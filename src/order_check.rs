@@ -0,0 +1,283 @@
+use std::cell::RefCell;
+use std::cmp::{Ord, Ordering};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{Link, Tree, TreeNode};
+
+/// Contradiction in what `Ord` of keys says, found by checked descent.
+/// Tree is left as it was when one is found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OrderError<K> {
+    /// `key.cmp(other)` is not the reverse of `other.cmp(key)`.
+    Asymmetric { key: K, other: K },
+    /// Key is not between keys of ancestors whose subtree it belongs to.
+    OutOfBounds { key: K, bound: K },
+}
+
+impl<K: fmt::Debug> fmt::Display for OrderError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::Asymmetric { key, other } => write!(
+                f,
+                "comparing {:?} with {:?} both ways gives contradicting answers",
+                key, other
+            ),
+            OrderError::OutOfBounds { key, bound } => {
+                write!(f, "key {:?} is on the wrong side of {:?}", key, bound)
+            }
+        }
+    }
+}
+
+impl<K: fmt::Debug> Error for OrderError<K> {}
+
+/// Contradiction found on the way down, pointing at nodes involved.
+pub(crate) enum Violation<K: Ord, V> {
+    /// Searched key and node compare inconsistently.
+    Asymmetric(Rc<RefCell<TreeNode<K, V>>>),
+    /// Searched key is on the wrong side of bound.
+    KeyOutOfBounds(Rc<RefCell<TreeNode<K, V>>>),
+    /// Node is on the wrong side of bound.
+    NodeOutOfBounds(Rc<RefCell<TreeNode<K, V>>>, Rc<RefCell<TreeNode<K, V>>>),
+}
+
+impl<K: Ord, V> Violation<K, V> {
+    fn to_error(&self, key: &K) -> OrderError<K>
+    where
+        K: Clone,
+    {
+        match self {
+            Violation::Asymmetric(node) => OrderError::Asymmetric {
+                key: key.clone(),
+                other: node.borrow().key.clone(),
+            },
+            Violation::KeyOutOfBounds(bound) => OrderError::OutOfBounds {
+                key: key.clone(),
+                bound: bound.borrow().key.clone(),
+            },
+            Violation::NodeOutOfBounds(node, bound) => OrderError::OutOfBounds {
+                key: node.borrow().key.clone(),
+                bound: bound.borrow().key.clone(),
+            },
+        }
+    }
+
+    /// Message for methods that can not return the error.
+    pub(crate) fn describe(&self) -> &'static str {
+        match self {
+            Violation::Asymmetric(_) => "Inconsistent Ord: keys compare differently both ways",
+            _ => "Inconsistent Ord: key is on the wrong side of its ancestor",
+        }
+    }
+}
+
+impl<K: Ord, V> Tree<K, V> {
+    /// Turns checking of key order on or off, it is off by default.
+    /// Checked descent compares every key a few more times.
+    ///
+    /// `checked_insert` and `checked_find_node` return contradictions in `Ord` as `OrderError`.
+    /// With checks on, `insert`, `find_node` and methods built on them, like `get` and `delete`,
+    /// can not return one, so they panic before tree is changed
+    /// instead of quietly building an inconsistent tree.
+    pub fn set_order_checks(&mut self, on: bool) {
+        self.check_order = on;
+    }
+
+    /// Returns whether key order is checked, see `set_order_checks`.
+    pub fn order_checks(&self) -> bool {
+        self.check_order
+    }
+
+    /// Inserts key-value like `insert`, but checks order of keys on the way,
+    /// whether checks are on or not. Tree is not changed if `Ord` contradicts itself.
+    pub fn checked_insert(&mut self, key: K, value: V) -> Result<Option<V>, OrderError<K>>
+    where
+        K: Clone,
+    {
+        let place = self
+            .descend_checked(&key)
+            .map_err(|violation| violation.to_error(&key))?;
+        Ok(self.insert_at(place, TreeNode::new(key, value)))
+    }
+
+    /// Finds node like `find_node`, but checks order of keys on the way.
+    pub fn checked_find_node(&self, key: &K) -> Result<Link<K, V>, OrderError<K>>
+    where
+        K: Clone,
+    {
+        let place = self
+            .descend_checked(key)
+            .map_err(|violation| violation.to_error(key))?;
+        Ok(place.filter(|node| key.cmp(&node.borrow().key) == Ordering::Equal))
+    }
+
+    /// Descends to node with given key or last node on the path,
+    /// checking key order if checks are on.
+    pub(crate) fn place_of(&self, key: &K) -> Result<Link<K, V>, Violation<K, V>> {
+        if !self.check_order {
+            return Ok(self.find_node_or_last(key));
+        }
+        self.descend_checked(key)
+    }
+
+    /// Same descent as `find_node_or_last`, but compares both ways
+    /// and keeps nodes bounding current subtree to check against.
+    fn descend_checked(&self, key: &K) -> Result<Link<K, V>, Violation<K, V>> {
+        let mut current = match &self.root {
+            None => return Ok(None),
+            Some(root) => Rc::clone(root),
+        };
+        let (mut lower, mut upper): (Link<K, V>, Link<K, V>) = (None, None);
+
+        loop {
+            let next = {
+                let node = current.borrow();
+                let order = key.cmp(&node.key);
+                if node.key.cmp(key) != order.reverse() {
+                    return Err(Violation::Asymmetric(Rc::clone(&current)));
+                }
+
+                let bounds = [(&lower, Ordering::Greater), (&upper, Ordering::Less)];
+                for (bound, side) in bounds.iter() {
+                    if let Some(bound) = bound {
+                        let bound_key = &bound.borrow().key;
+                        if node.key.cmp(bound_key) != *side {
+                            let node = Rc::clone(&current);
+                            return Err(Violation::NodeOutOfBounds(node, Rc::clone(bound)));
+                        }
+                        if key.cmp(bound_key) != *side {
+                            return Err(Violation::KeyOutOfBounds(Rc::clone(bound)));
+                        }
+                    }
+                }
+
+                match order {
+                    Ordering::Less => {
+                        upper = Some(Rc::clone(&current));
+                        node.left.clone()
+                    }
+                    Ordering::Greater => {
+                        lower = Some(Rc::clone(&current));
+                        node.right.clone()
+                    }
+                    Ordering::Equal => None,
+                }
+            };
+
+            match next {
+                Some(next) => current = next,
+                None => return Ok(Some(current)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OrderError;
+    use crate::Tree;
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    /// Says every other key is greater, so `a < b` and `b < a`.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Humble(i64);
+
+    impl PartialOrd for Humble {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Humble {
+        fn cmp(&self, other: &Self) -> Ordering {
+            if self.0 == other.0 {
+                Ordering::Equal
+            } else {
+                Ordering::Less
+            }
+        }
+    }
+
+    thread_local! {
+        static REVERSED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// Order of these can be flipped after they are in tree.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Fickle(i64);
+
+    impl PartialOrd for Fickle {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Fickle {
+        fn cmp(&self, other: &Self) -> Ordering {
+            match REVERSED.with(Cell::get) {
+                false => self.0.cmp(&other.0),
+                true => other.0.cmp(&self.0),
+            }
+        }
+    }
+
+    #[test]
+    fn asymmetric() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.checked_insert(Humble(1), 1), Ok(None));
+        assert_eq!(
+            tree.checked_insert(Humble(2), 2),
+            Err(OrderError::Asymmetric {
+                key: Humble(2),
+                other: Humble(1)
+            })
+        );
+        assert_eq!(tree.len(), 1);
+
+        tree.set_order_checks(true);
+        let result = catch_unwind(AssertUnwindSafe(|| tree.insert(Humble(3), 3)));
+        assert!(result.is_err());
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.checked_insert(Humble(1), 10), Ok(Some(1)));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        REVERSED.with(|reversed| reversed.set(false));
+        let mut tree = Tree::new();
+        for key in &[5, 3, 8, 4] {
+            tree.insert(Fickle(*key), *key);
+        }
+        assert!(tree.checked_find_node(&Fickle(4)).unwrap().is_some());
+        assert!(tree.checked_find_node(&Fickle(6)).unwrap().is_none());
+
+        // Now 4 goes right of 5 and meets 8, which is below its bound 5
+        REVERSED.with(|reversed| reversed.set(true));
+        assert_eq!(
+            tree.checked_find_node(&Fickle(4)).err(),
+            Some(OrderError::OutOfBounds {
+                key: Fickle(8),
+                bound: Fickle(5)
+            })
+        );
+        tree.set_order_checks(true);
+        let result = catch_unwind(AssertUnwindSafe(|| tree.insert(Fickle(7), 7)));
+        assert!(result.is_err());
+        assert_eq!(tree.len(), 4);
+        REVERSED.with(|reversed| reversed.set(false));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn checks_off_by_default() {
+        let mut tree = Tree::new();
+        assert!(!tree.order_checks());
+        tree.insert(Humble(1), 1);
+        tree.insert(Humble(2), 2);
+        assert_eq!(tree.len(), 2);
+    }
+}
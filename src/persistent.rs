@@ -0,0 +1,335 @@
+use std::cmp::{Ord, Ordering};
+use std::rc::Rc;
+
+/// Immutable version of My Little Tree.
+/// Every change returns new version of tree, old versions stay readable forever.
+/// Versions share all subtrees that were not touched, only path to changed node is copied.
+/// There are no parent links, otherwise nodes could not be shared.
+pub struct PersistentTree<K: Ord, V> {
+    size: usize,
+    root: Option<Rc<PersistentNode<K, V>>>,
+}
+
+struct PersistentNode<K: Ord, V> {
+    key: K,
+    value: V,
+    left: Option<Rc<PersistentNode<K, V>>>,
+    right: Option<Rc<PersistentNode<K, V>>>,
+}
+
+impl<K: Ord, V> PersistentTree<K, V> {
+    /// Creates empty tree.
+    pub fn new() -> Self {
+        PersistentTree {
+            size: 0,
+            root: None,
+        }
+    }
+
+    /// Returns number of nodes in tree.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Checks if tree has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Returns value stored by given key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut current = self.root.as_deref();
+        while let Some(node) = current {
+            current = match key.cmp(&node.key) {
+                Ordering::Less => node.left.as_deref(),
+                Ordering::Greater => node.right.as_deref(),
+                Ordering::Equal => return Some(&node.value),
+            };
+        }
+        None
+    }
+
+    /// Checks if there is given key in tree.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns entry with least key in tree.
+    pub fn least(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(left) = node.left.as_deref() {
+            node = left;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// Returns entry with greatest key in tree.
+    pub fn greatest(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(right) = node.right.as_deref() {
+            node = right;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// Returns iterator over entries from least to greatest key.
+    pub fn iter(&self) -> PersistentIter<'_, K, V> {
+        let mut iter = PersistentIter { stack: vec![] };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> PersistentTree<K, V> {
+    /// Returns new version with inserted key-value, this version is not changed.
+    /// If key was there, its value is replaced in new version.
+    pub fn insert(&self, key: K, value: V) -> Self {
+        let (root, added) = insert_r(&self.root, key, value);
+        PersistentTree {
+            size: if added { self.size + 1 } else { self.size },
+            root: Some(root),
+        }
+    }
+
+    /// Returns new version without given key, this version is not changed.
+    /// If there was no such key, returned version shares everything with this one.
+    pub fn delete(&self, key: &K) -> Self {
+        match delete_r(&self.root, key) {
+            None => self.clone(),
+            Some(root) => PersistentTree {
+                size: self.size - 1,
+                root,
+            },
+        }
+    }
+}
+
+/// Copies node with new children.
+fn copy_with<K: Ord + Clone, V: Clone>(
+    node: &PersistentNode<K, V>,
+    left: Option<Rc<PersistentNode<K, V>>>,
+    right: Option<Rc<PersistentNode<K, V>>>,
+) -> Rc<PersistentNode<K, V>> {
+    Rc::new(PersistentNode {
+        key: node.key.clone(),
+        value: node.value.clone(),
+        left,
+        right,
+    })
+}
+
+/// Recursive insert with path copying.
+/// Returns new root of subtree and whether node count grew.
+fn insert_r<K: Ord + Clone, V: Clone>(
+    link: &Option<Rc<PersistentNode<K, V>>>,
+    key: K,
+    value: V,
+) -> (Rc<PersistentNode<K, V>>, bool) {
+    let node = match link {
+        None => {
+            let node = PersistentNode {
+                key,
+                value,
+                left: None,
+                right: None,
+            };
+            return (Rc::new(node), true);
+        }
+        Some(node) => node,
+    };
+
+    match key.cmp(&node.key) {
+        Ordering::Less => {
+            let (left, added) = insert_r(&node.left, key, value);
+            (copy_with(node, Some(left), node.right.clone()), added)
+        }
+        Ordering::Greater => {
+            let (right, added) = insert_r(&node.right, key, value);
+            (copy_with(node, node.left.clone(), Some(right)), added)
+        }
+        Ordering::Equal => {
+            let node = PersistentNode {
+                key: node.key.clone(),
+                value,
+                left: node.left.clone(),
+                right: node.right.clone(),
+            };
+            (Rc::new(node), false)
+        }
+    }
+}
+
+/// Recursive delete with path copying.
+/// Returns None if key was not found, otherwise new root of subtree.
+fn delete_r<K: Ord + Clone, V: Clone>(
+    link: &Option<Rc<PersistentNode<K, V>>>,
+    key: &K,
+) -> Option<Option<Rc<PersistentNode<K, V>>>> {
+    let node = link.as_ref()?;
+
+    match key.cmp(&node.key) {
+        Ordering::Less => {
+            let left = delete_r(&node.left, key)?;
+            Some(Some(copy_with(node, left, node.right.clone())))
+        }
+        Ordering::Greater => {
+            let right = delete_r(&node.right, key)?;
+            Some(Some(copy_with(node, node.left.clone(), right)))
+        }
+        Ordering::Equal => match (&node.left, &node.right) {
+            (None, right) => Some(right.clone()),
+            (left, None) => Some(left.clone()),
+            // Same lazy trick as in Tree: left subtree goes under least node of right one
+            (Some(left), Some(right)) => Some(Some(hang_left(right, Rc::clone(left)))),
+        },
+    }
+}
+
+/// Copies left spine of subtree and hangs given node under its least node.
+fn hang_left<K: Ord + Clone, V: Clone>(
+    node: &Rc<PersistentNode<K, V>>,
+    hanged: Rc<PersistentNode<K, V>>,
+) -> Rc<PersistentNode<K, V>> {
+    let left = match &node.left {
+        None => hanged,
+        Some(left) => hang_left(left, hanged),
+    };
+    copy_with(node, Some(left), node.right.clone())
+}
+
+/// Clone is O(1): both trees share all nodes.
+impl<K: Ord, V> Clone for PersistentTree<K, V> {
+    fn clone(&self) -> Self {
+        PersistentTree {
+            size: self.size,
+            root: self.root.clone(),
+        }
+    }
+}
+
+impl<K: Ord, V> Default for PersistentTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterator over entries of persistent tree.
+/// Unlike `TreeNodeIterator` it can not go up, so it keeps path in stack.
+pub struct PersistentIter<'a, K: Ord, V> {
+    stack: Vec<&'a PersistentNode<K, V>>,
+}
+
+impl<'a, K: Ord, V> PersistentIter<'a, K, V> {
+    fn push_left(&mut self, mut node: Option<&'a PersistentNode<K, V>>) {
+        while let Some(lq) = node {
+            self.stack.push(lq);
+            node = lq.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for PersistentIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a PersistentTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = PersistentIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PersistentTree;
+    use std::rc::Rc;
+
+    fn paper_tree() -> PersistentTree<i64, i64> {
+        let mut tree = PersistentTree::new();
+        for i in &[100, 50, 10, 70, 60, 99, 200, 115, 300] {
+            tree = tree.insert(*i, *i);
+        }
+        tree
+    }
+
+    fn keys(tree: &PersistentTree<i64, i64>) -> Vec<i64> {
+        tree.iter().map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn insert_keeps_old_version() {
+        let empty = PersistentTree::new();
+        let one = empty.insert(1, "one");
+        let two = one.insert(2, "two");
+        let replaced = two.insert(1, "uno");
+
+        assert!(empty.is_empty());
+        assert_eq!(one.len(), 1);
+        assert_eq!(two.len(), 2);
+        assert_eq!(replaced.len(), 2);
+
+        assert_eq!(one.get(&1), Some(&"one"));
+        assert_eq!(one.get(&2), None);
+        assert_eq!(two.get(&1), Some(&"one"));
+        assert_eq!(replaced.get(&1), Some(&"uno"));
+        assert_eq!(replaced.get(&2), Some(&"two"));
+    }
+
+    #[test]
+    fn delete_keeps_old_version() {
+        let tree = paper_tree();
+        let all = keys(&tree);
+
+        for deleted in all.iter() {
+            let version = tree.delete(deleted);
+            assert_eq!(version.len(), 8);
+            assert!(!version.contains_key(deleted));
+            assert_eq!(
+                keys(&version),
+                all.iter()
+                    .cloned()
+                    .filter(|k| k != deleted)
+                    .collect::<Vec<i64>>()
+            );
+        }
+
+        assert_eq!(keys(&tree), all);
+        assert_eq!(tree.delete(&1000).len(), 9);
+    }
+
+    #[test]
+    fn untouched_subtrees_shared() {
+        let tree = paper_tree();
+        let changed = tree.insert(5, 5);
+
+        let old_root = tree.root.as_ref().unwrap();
+        let new_root = changed.root.as_ref().unwrap();
+        assert!(!Rc::ptr_eq(old_root, new_root));
+        assert!(Rc::ptr_eq(
+            old_root.right.as_ref().unwrap(),
+            new_root.right.as_ref().unwrap()
+        ));
+
+        let copy = changed.clone();
+        assert!(Rc::ptr_eq(new_root, copy.root.as_ref().unwrap()));
+    }
+
+    #[test]
+    fn least_greatest() {
+        let tree = paper_tree();
+        assert_eq!(tree.least(), Some((&10, &10)));
+        assert_eq!(tree.greatest(), Some((&300, &300)));
+        assert_eq!(PersistentTree::<i64, i64>::new().least(), None);
+
+        let sum: i64 = (&tree).into_iter().map(|(_, v)| v).sum();
+        assert_eq!(sum, 1004);
+    }
+}
@@ -0,0 +1,176 @@
+use std::cmp::Ord;
+use std::fmt::{self, Display};
+
+use crate::{Link, Tree, TreeNode};
+
+type Label<'a, K, V> = Box<dyn Fn(&K, &V) -> String + 'a>;
+
+/// Printable drawing of tree, see `Tree::pretty`.
+pub struct Pretty<'a, K: Ord, V> {
+    tree: &'a Tree<K, V>,
+    label: Label<'a, K, V>,
+    /// Deeper nodes are folded, root is at depth 0.
+    max_depth: Option<usize>,
+}
+
+impl<K: Ord, V> Tree<K, V> {
+    /// Returns drawing of tree for printing, nodes labelled as `key=value`.
+    /// Every node is followed by its left and right subtrees, drawn one level deeper:
+    ///
+    /// ```text
+    /// (root) 2=two
+    /// ├── L 1=one
+    /// └── R 3=three
+    /// ```
+    pub fn pretty(&self) -> Pretty<'_, K, V>
+    where
+        K: Display,
+        V: Display,
+    {
+        self.pretty_with(|key, value| format!("{}={}", key, value))
+    }
+
+    /// Returns drawing of tree for printing, labelling nodes with given function.
+    pub fn pretty_with<'a, F>(&'a self, label: F) -> Pretty<'a, K, V>
+    where
+        F: Fn(&K, &V) -> String + 'a,
+    {
+        Pretty {
+            tree: self,
+            label: Box::new(label),
+            max_depth: None,
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Pretty<'a, K, V> {
+    /// Folds subtrees deeper than given depth into a single line with their size.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+}
+
+impl<'a, K: Ord, V> Display for Pretty<'a, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let root = match &self.tree.root {
+            None => return writeln!(f, "(empty)"),
+            Some(root) => root,
+        };
+
+        // Lines are drawn in pre-order, with prefix of lines drawn by ancestors
+        let mut stack: Vec<Line<K, V>> = vec![];
+        {
+            let root = root.borrow();
+            writeln!(f, "(root) {}", (self.label)(&root.key, &root.value))?;
+            push_children(&mut stack, &root, String::new(), 1);
+        }
+        while let Some((link, prefix, last, side, depth)) = stack.pop() {
+            let connector = if last { "└── " } else { "├── " };
+            let node = match link {
+                None => {
+                    writeln!(f, "{}{}{} ·", prefix, connector, side)?;
+                    continue;
+                }
+                Some(node) => node,
+            };
+
+            let node = node.borrow();
+            if self.max_depth.is_some_and(|max| depth > max) {
+                let noun = if node.count == 1 { "node" } else { "nodes" };
+                writeln!(
+                    f,
+                    "{}{}{} … ({} {})",
+                    prefix, connector, side, node.count, noun
+                )?;
+                continue;
+            }
+            writeln!(
+                f,
+                "{}{}{} {}",
+                prefix,
+                connector,
+                side,
+                (self.label)(&node.key, &node.value)
+            )?;
+
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            push_children(&mut stack, &node, prefix, depth + 1);
+        }
+        Ok(())
+    }
+}
+
+/// Child to draw: node or missing child, prefix, whether it is the last child, side and depth.
+type Line<K, V> = (Link<K, V>, String, bool, &'static str, usize);
+
+/// Pushes children of node, so left one is drawn first. Leaves have nothing to draw.
+fn push_children<K: Ord, V>(
+    stack: &mut Vec<Line<K, V>>,
+    node: &TreeNode<K, V>,
+    prefix: String,
+    depth: usize,
+) {
+    if node.left.is_some() || node.right.is_some() {
+        stack.push((node.right.clone(), prefix.clone(), true, "R", depth));
+        stack.push((node.left.clone(), prefix, false, "L", depth));
+    }
+}
+
+/// Same as `Tree::pretty`.
+impl<K: Ord + Display, V: Display> Display for Tree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pretty().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::paper_tree;
+    use crate::{Mode, Tree};
+
+    #[test]
+    fn draws_shape() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        tree.insert(400, 400);
+        assert_eq!(
+            tree.to_string(),
+            "\
+(root) 100=100
+├── L 50=50
+│   ├── L 10=10
+│   └── R 70=70
+│       ├── L 60=60
+│       └── R 99=99
+└── R 200=200
+    ├── L 115=115
+    └── R 300=300
+        ├── L ·
+        └── R 400=400
+"
+        );
+        assert_eq!(Tree::<i64, i64>::new().to_string(), "(empty)\n");
+    }
+
+    #[test]
+    fn depth_and_labels() {
+        let tree = paper_tree(Mode::Unbalanced);
+        let text = tree
+            .pretty_with(|key, _| format!("<{}>", key))
+            .max_depth(1)
+            .to_string();
+        assert_eq!(
+            text,
+            "\
+(root) <100>
+├── L <50>
+│   ├── L … (1 node)
+│   └── R … (3 nodes)
+└── R <200>
+    ├── L … (1 node)
+    └── R … (1 node)
+"
+        );
+        assert_eq!(tree.pretty().max_depth(0).to_string().lines().count(), 3);
+    }
+}
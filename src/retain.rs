@@ -0,0 +1,141 @@
+use std::cell::RefCell;
+use std::cmp::Ord;
+use std::rc::Rc;
+
+use crate::{Link, Tree, TreeNode, TreeNodeIterator};
+
+/// Bulk removal in a single walk from least to greatest node.
+impl<K: Ord, V> Tree<K, V> {
+    /// Keeps only entries for which predicate returns true.
+    /// Predicate is called once for every entry in key order.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.extract_if(|k, v| !f(k, v)).for_each(drop);
+    }
+
+    /// Returns lazy iterator removing and yielding nodes for which predicate returns true.
+    /// Nodes are visited in key order; ones not reached before iterator is dropped stay in tree.
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        ExtractIf {
+            next_node: self.least_node(),
+            tree: self,
+            pred,
+        }
+    }
+}
+
+/// Iterator returned by `Tree::extract_if`.
+/// Like `delete`, it yields detached nodes.
+pub struct ExtractIf<'a, K: Ord, V, F>
+where
+    F: FnMut(&K, &mut V) -> bool,
+{
+    tree: &'a mut Tree<K, V>,
+    /// Next node to check, None once walk is over.
+    next_node: Link<K, V>,
+    pred: F,
+}
+
+impl<'a, K: Ord, V, F> Iterator for ExtractIf<'a, K, V, F>
+where
+    F: FnMut(&K, &mut V) -> bool,
+{
+    type Item = Rc<RefCell<TreeNode<K, V>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.next_node.take() {
+            // Predicate may change value, and node may be shared with snapshots
+            let node = self.tree.own(&node);
+
+            // Successor survives unlinking of current node, so it is safe to look it up first.
+            // It still can be copied on the way, `own` follows such copies.
+            self.next_node = TreeNodeIterator::next_of(&node);
+
+            let matched = {
+                let tree_node = &mut *node.borrow_mut();
+                (self.pred)(&tree_node.key, &mut tree_node.value)
+            };
+
+            if matched {
+                let node = self.tree.unlink(&node);
+                self.tree.notify_removed(&node);
+                self.tree.finish_change();
+                return Some(node);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::keys;
+    use crate::{Mode, Tree};
+
+    #[test]
+    fn retain_even() {
+        for mode in &[Mode::Unbalanced, Mode::Splay] {
+            let mut tree = Tree::with_mode(*mode);
+            for i in &[100, 50, 10, 70, 60, 99, 200, 115, 300, 1, 2, 3] {
+                tree.insert(*i, *i);
+            }
+
+            tree.retain(|k, _| k % 2 == 0);
+            assert_eq!(tree.len(), 8);
+            assert_eq!(keys(tree), vec![2, 10, 50, 60, 70, 100, 200, 300]);
+        }
+    }
+
+    #[test]
+    fn retain_updates_values() {
+        let mut tree = Tree::new();
+        for i in 0..10 {
+            tree.insert(i, i);
+        }
+
+        tree.retain(|_, v| {
+            *v *= 10;
+            *v < 50
+        });
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.get(&4), Some(40));
+        assert_eq!(tree.get(&5), None);
+    }
+
+    #[test]
+    fn retain_nothing() {
+        let mut tree = Tree::new();
+        for i in &[5, 3, 8, 1, 4, 7, 9] {
+            tree.insert(*i, *i);
+        }
+
+        tree.retain(|_, _| false);
+        assert!(tree.is_empty());
+        assert!(tree.root.is_none());
+    }
+
+    #[test]
+    fn extract_lazy() {
+        let mut tree = Tree::new();
+        for i in &[5, 3, 8, 1, 4, 7, 9] {
+            tree.insert(*i, *i);
+        }
+
+        let first: Vec<i64> = tree
+            .extract_if(|k, _| k % 2 == 1)
+            .take(2)
+            .map(|n| n.borrow().key)
+            .collect();
+        assert_eq!(first, vec![1, 3]);
+        assert_eq!(tree.len(), 5);
+
+        let rest = tree.extract_if(|k, _| k % 2 == 1).count();
+        assert_eq!(rest, 3);
+        assert_eq!(keys(tree), vec![4, 8]);
+    }
+}
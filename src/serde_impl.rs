@@ -0,0 +1,152 @@
+use std::cmp::Ord;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, Error, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::batch::build_r;
+use crate::{Mode, Tree, TreeNodeIterator};
+
+/// What to do when deserialized map has the same key twice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Fail deserialization. Used by `Deserialize` impl of `Tree`.
+    Reject,
+    /// Keep value that came first.
+    KeepFirst,
+    /// Keep value that came last, like repeated `insert` would.
+    KeepLast,
+}
+
+/// Tree is serialized as a map, in key order.
+impl<K: Ord + Serialize, V: Serialize> Serialize for Tree<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.size))?;
+        let mut current = self.least_node();
+        while let Some(node) = current {
+            {
+                let node = node.borrow();
+                map.serialize_entry(&node.key, &node.value)?;
+            }
+            current = TreeNodeIterator::next_of(&node);
+        }
+        map.end()
+    }
+}
+
+/// Tree is deserialized from a map, duplicate keys are rejected.
+/// See `Tree::deserialize_with` for other ways to handle them.
+impl<'de, K: Ord + Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de> for Tree<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::deserialize_with(deserializer, DuplicateKeys::Reject)
+    }
+}
+
+impl<K: Ord, V> Tree<K, V> {
+    /// Deserializes tree from a map, handling duplicate keys as told.
+    /// Loaded tree is balanced, its mode is unbalanced.
+    pub fn deserialize_with<'de, D>(
+        deserializer: D,
+        duplicates: DuplicateKeys,
+    ) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+    {
+        deserializer.deserialize_map(TreeVisitor {
+            duplicates,
+            marker: PhantomData,
+        })
+    }
+}
+
+struct TreeVisitor<K, V> {
+    duplicates: DuplicateKeys,
+    marker: PhantomData<fn() -> (K, V)>,
+}
+
+impl<'de, K: Ord + Deserialize<'de>, V: Deserialize<'de>> Visitor<'de> for TreeVisitor<K, V> {
+    type Value = Tree<K, V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut entries: Vec<(K, V)> =
+            Vec::with_capacity(access.size_hint().unwrap_or(0).min(4096));
+        while let Some(entry) = access.next_entry()? {
+            entries.push(entry);
+        }
+
+        // Sort is stable, so equal keys keep the order they came in
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut unique: Vec<(K, V)> = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            match unique.last_mut() {
+                Some(last) if last.0 == key => match self.duplicates {
+                    DuplicateKeys::Reject => return Err(A::Error::custom("duplicate key in map")),
+                    DuplicateKeys::KeepFirst => {}
+                    DuplicateKeys::KeepLast => last.1 = value,
+                },
+                _ => unique.push((key, value)),
+            }
+        }
+
+        let mut tree = Tree::with_mode(Mode::Unbalanced);
+        let generation = tree.cow.generation();
+        tree.size = unique.len();
+        tree.root = build_r(&mut unique.into_iter(), tree.size, generation);
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DuplicateKeys;
+    use crate::Tree;
+
+    #[test]
+    fn json_round_trip() {
+        let mut tree = Tree::new();
+        for key in &[5, 3, 8, 1] {
+            tree.insert(key.to_string(), *key);
+        }
+
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(json, r#"{"1":1,"3":3,"5":5,"8":8}"#);
+
+        let loaded: Tree<String, i64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.len(), 4);
+        assert_eq!(loaded.get(&"8".to_string()), Some(8));
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+    }
+
+    #[test]
+    fn nested() {
+        let json = r#"{"b":{"x":1},"a":{}}"#;
+        let tree: Tree<String, Tree<String, i64>> = serde_json::from_str(json).unwrap();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(
+            serde_json::to_string(&tree).unwrap(),
+            r#"{"a":{},"b":{"x":1}}"#
+        );
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let json = r#"{"a":1,"b":2,"a":3}"#;
+        assert!(serde_json::from_str::<Tree<String, i64>>(json).is_err());
+
+        let load = |duplicates| {
+            let mut deserializer = serde_json::Deserializer::from_str(json);
+            let tree: Tree<String, i64> =
+                Tree::deserialize_with(&mut deserializer, duplicates).unwrap();
+            tree.get(&"a".to_string())
+        };
+        assert_eq!(load(DuplicateKeys::KeepFirst), Some(1));
+        assert_eq!(load(DuplicateKeys::KeepLast), Some(3));
+    }
+}
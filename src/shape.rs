@@ -0,0 +1,408 @@
+use std::cell::RefCell;
+use std::cmp::Ord;
+use std::fmt::{Display, Write as _};
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::binary::{read_record, write_record};
+use crate::{Decode, Encode, FormatError, Link, Mode, Tree, TreeNode};
+
+/// First bytes of every saved shape.
+const MAGIC: &[u8; 4] = b"MLTS";
+
+/// Version of format written by `Tree::write_shape_to`.
+const VERSION: u16 = 1;
+
+/// Free child place met while rebuilding shape.
+struct Place<K: Ord, V> {
+    /// None for root.
+    parent: Link<K, V>,
+    to_left: bool,
+    /// Nodes whose keys bound keys of this place.
+    lower: Link<K, V>,
+    upper: Link<K, V>,
+}
+
+/// Saving exact shape of tree, for reproducing bugs that depend on it.
+/// Tree is written in pre-order, each node followed by its left and right subtrees,
+/// missing children are written as null markers.
+impl<K: Ord, V> Tree<K, V> {
+    /// Calls visitor for every node and missing child, in pre-order.
+    fn visit_shape<E, F>(&self, mut visit: F) -> Result<(), E>
+    where
+        F: FnMut(Option<&TreeNode<K, V>>) -> Result<(), E>,
+    {
+        let mut stack = vec![self.root.clone()];
+        while let Some(link) = stack.pop() {
+            match link {
+                None => visit(None)?,
+                Some(node) => {
+                    let node = node.borrow();
+                    visit(Some(&node))?;
+                    stack.push(node.right.clone());
+                    stack.push(node.left.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuilds tree from pre-order entries, None stands for missing child.
+    /// Checks that keys are in order, not that nothing follows the last node.
+    fn build_shape<F>(mode: Mode, mut next: F) -> Result<Self, FormatError>
+    where
+        F: FnMut() -> Result<Option<(K, V)>, FormatError>,
+    {
+        let mut tree = Tree::with_mode(mode);
+        let generation = tree.cow.generation();
+        let mut nodes = vec![];
+
+        let mut places = vec![Place {
+            parent: None,
+            to_left: false,
+            lower: None,
+            upper: None,
+        }];
+        while let Some(place) = places.pop() {
+            let (key, value) = match next()? {
+                None => continue,
+                Some(entry) => entry,
+            };
+            let above_lower = place.lower.as_ref().map_or(true, |l| l.borrow().key < key);
+            let below_upper = place.upper.as_ref().map_or(true, |u| u.borrow().key > key);
+            if !above_lower || !below_upper {
+                return Err(FormatError::Unordered);
+            }
+
+            let mut node = TreeNode::new(key, value);
+            node.generation = generation;
+            node.parent = place.parent.clone();
+            let node = Rc::new(RefCell::new(node));
+            match &place.parent {
+                None => tree.root = Some(Rc::clone(&node)),
+                Some(parent) if place.to_left => parent.borrow_mut().left = Some(Rc::clone(&node)),
+                Some(parent) => parent.borrow_mut().right = Some(Rc::clone(&node)),
+            }
+
+            // Right place goes first, so left subtree is read first
+            places.push(Place {
+                parent: Some(Rc::clone(&node)),
+                to_left: false,
+                lower: Some(Rc::clone(&node)),
+                upper: place.upper,
+            });
+            places.push(Place {
+                parent: Some(Rc::clone(&node)),
+                to_left: true,
+                lower: place.lower,
+                upper: Some(Rc::clone(&node)),
+            });
+            nodes.push(node);
+        }
+
+        // Children come after parents in pre-order
+        for node in nodes.iter().rev() {
+            node.borrow_mut().recount();
+        }
+        tree.size = nodes.len();
+        Ok(tree)
+    }
+
+    /// Returns text form of shape: nodes as `key=value`, missing children as `.`,
+    /// separated by spaces. Backslash, `=` and whitespace in keys and values are escaped,
+    /// as `\\`, `\=`, `\s` for space and `\u{..}` for other whitespace.
+    pub fn to_shape_string(&self) -> String
+    where
+        K: Display,
+        V: Display,
+    {
+        let mut text = String::new();
+        let _ = self.visit_shape(|node| {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            match node {
+                None => text.push('.'),
+                Some(node) => {
+                    escape_into(&mut text, &node.key.to_string());
+                    text.push('=');
+                    escape_into(&mut text, &node.value.to_string());
+                }
+            }
+            Ok::<(), ()>(())
+        });
+        text
+    }
+
+    /// Rebuilds tree of exactly the same shape from `to_shape_string` text.
+    pub fn from_shape_str(text: &str, mode: Mode) -> Result<Self, FormatError>
+    where
+        K: FromStr,
+        V: FromStr,
+    {
+        let mut tokens = text.split_whitespace();
+        let tree = Self::build_shape(mode, || {
+            let token = tokens.next().ok_or(FormatError::BadShape)?;
+            if token == "." {
+                return Ok(None);
+            }
+            let (key, value) = unescape_token(token)?;
+            let key = key.parse().map_err(|_| FormatError::BadRecord)?;
+            let value = value.parse().map_err(|_| FormatError::BadRecord)?;
+            Ok(Some((key, value)))
+        })?;
+
+        if tokens.next().is_some() {
+            return Err(FormatError::BadShape);
+        }
+        Ok(tree)
+    }
+
+    /// Saves exact shape of tree, along with its mode.
+    ///
+    /// Format, all numbers little endian:
+    /// - magic bytes `MLTS`;
+    /// - format version, u16;
+    /// - mode, 0 for unbalanced and 1 for splay;
+    /// - nodes in pre-order: null marker 0 for missing child, or node marker 1 followed by
+    ///   key length as u32, key bytes, value length as u32, value bytes.
+    pub fn write_shape_to<W: Write>(&self, mut writer: W) -> io::Result<()>
+    where
+        K: Encode,
+        V: Encode,
+    {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        let mode = match self.mode {
+            Mode::Unbalanced => 0,
+            Mode::Splay => 1,
+        };
+        writer.write_all(&[mode])?;
+
+        let mut buf = vec![];
+        self.visit_shape(|node| match node {
+            None => writer.write_all(&[0]),
+            Some(node) => {
+                writer.write_all(&[1])?;
+                write_record(&mut writer, &mut buf, &node.key)?;
+                write_record(&mut writer, &mut buf, &node.value)
+            }
+        })?;
+        writer.flush()
+    }
+
+    /// Loads tree saved by `write_shape_to`, with the same shape and mode.
+    pub fn read_shape_from<R: Read>(mut reader: R) -> Result<Self, FormatError>
+    where
+        K: Decode,
+        V: Decode,
+    {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+
+        let mut version = [0; 2];
+        reader.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        let mode = match byte[0] {
+            0 => Mode::Unbalanced,
+            1 => Mode::Splay,
+            _ => return Err(FormatError::BadShape),
+        };
+
+        let mut buf = vec![];
+        let tree = Self::build_shape(mode, || {
+            let mut marker = [0];
+            reader.read_exact(&mut marker)?;
+            match marker[0] {
+                0 => Ok(None),
+                1 => {
+                    let key = read_record(&mut reader, &mut buf)?;
+                    let value = read_record(&mut reader, &mut buf)?;
+                    Ok(Some((key, value)))
+                }
+                _ => Err(FormatError::BadShape),
+            }
+        })?;
+
+        if reader.read(&mut byte)? != 0 {
+            return Err(FormatError::BadShape);
+        }
+        Ok(tree)
+    }
+}
+
+/// Writes key or value of text shape, escaping characters that would break tokens.
+fn escape_into(text: &mut String, raw: &str) {
+    for c in raw.chars() {
+        match c {
+            '\\' => text.push_str("\\\\"),
+            '=' => text.push_str("\\="),
+            ' ' => text.push_str("\\s"),
+            c if c.is_whitespace() => {
+                let _ = write!(text, "\\u{{{:x}}}", c as u32);
+            }
+            c => text.push(c),
+        }
+    }
+}
+
+/// Splits `key=value` token at the first unescaped `=` and unescapes both halves.
+fn unescape_token(token: &str) -> Result<(String, String), FormatError> {
+    let mut key = None;
+    let mut current = String::new();
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '=' if key.is_none() => key = Some(std::mem::take(&mut current)),
+            '=' => return Err(FormatError::BadShape),
+            '\\' => match chars.next().ok_or(FormatError::BadShape)? {
+                '\\' => current.push('\\'),
+                '=' => current.push('='),
+                's' => current.push(' '),
+                'u' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or(FormatError::BadShape)?;
+                    let code = rest
+                        .strip_prefix('{')
+                        .filter(|_| end > 0)
+                        .and_then(|_| u32::from_str_radix(&rest[1..end], 16).ok())
+                        .and_then(std::char::from_u32)
+                        .ok_or(FormatError::BadShape)?;
+                    current.push(code);
+                    chars = rest[end + 1..].chars();
+                }
+                _ => return Err(FormatError::BadShape),
+            },
+            c => current.push(c),
+        }
+    }
+    let key = key.ok_or(FormatError::BadShape)?;
+    Ok((key, current))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::paper_tree;
+    use crate::{FormatError, Mode, Tree};
+
+    const PAPER: &str =
+        "100=100 50=50 10=10 . . 70=70 60=60 . . 99=99 . . 200=200 115=115 . . 300=300 . .";
+
+    #[test]
+    fn text_round_trip() {
+        assert_eq!(paper_tree(Mode::Unbalanced).to_shape_string(), PAPER);
+
+        let rebuilt: Tree<i64, i64> = Tree::from_shape_str(PAPER, Mode::Unbalanced).unwrap();
+        assert_eq!(rebuilt.validate(), Ok(()));
+        assert_eq!(rebuilt.len(), 9);
+        assert_eq!(rebuilt.to_shape_string(), PAPER);
+        assert_eq!(Tree::<i64, i64>::new().to_shape_string(), ".");
+    }
+
+    #[test]
+    fn escaped_text_round_trip() {
+        let mut tree = Tree::new();
+        let values = ["two words", "a=b", "back\\slash", "tab\tand\nline", ".", ""];
+        for (key, value) in values.iter().enumerate() {
+            tree.insert(key.to_string(), value.to_string());
+        }
+        tree.insert("=".to_string(), " ".to_string());
+
+        let text = tree.to_shape_string();
+        assert!(text.contains("two\\swords"));
+        let rebuilt: Tree<String, String> = Tree::from_shape_str(&text, Mode::Unbalanced).unwrap();
+        assert_eq!(rebuilt.to_shape_string(), text);
+        assert_eq!(rebuilt.get(&"0".to_string()), Some("two words".to_string()));
+        assert_eq!(rebuilt.get(&"=".to_string()), Some(" ".to_string()));
+        for (key, value) in values.iter().enumerate() {
+            assert_eq!(rebuilt.get(&key.to_string()), Some(value.to_string()));
+        }
+    }
+
+    #[test]
+    fn delete_regression() {
+        // Root with both children, successor has right child
+        let mut tree: Tree<i64, i64> = Tree::from_shape_str(PAPER, Mode::Unbalanced).unwrap();
+        tree.delete(&50);
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(
+            tree.to_shape_string(),
+            "100=100 70=70 60=60 10=10 . . . 99=99 . . 200=200 115=115 . . 300=300 . ."
+        );
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut tree: Tree<i64, i64> = Tree::with_mode(Mode::Splay);
+        for key in &[5, 3, 8, 1, 4, 7, 9, 2, 6] {
+            tree.insert(*key, *key * 10);
+        }
+        tree.delete(&4);
+
+        let mut bytes = vec![];
+        tree.write_shape_to(&mut bytes).unwrap();
+        let rebuilt: Tree<i64, i64> = Tree::read_shape_from(&bytes[..]).unwrap();
+        assert_eq!(rebuilt.validate(), Ok(()));
+        assert_eq!(rebuilt.mode(), Mode::Splay);
+        assert_eq!(rebuilt.to_shape_string(), tree.to_shape_string());
+    }
+
+    #[test]
+    fn deep_shape() {
+        // Every node is right child of previous one
+        let mut text: String = (0..100_000).map(|key| format!("{}=0 . ", key)).collect();
+        text.push('.');
+        let tree: Tree<i64, i64> = Tree::from_shape_str(&text, Mode::Unbalanced).unwrap();
+        assert_eq!(tree.len(), 100_000);
+        assert_eq!(tree.root.as_ref().unwrap().borrow().count, 100_000);
+
+        let mut bytes = vec![];
+        tree.write_shape_to(&mut bytes).unwrap();
+        let rebuilt: Tree<i64, i64> = Tree::read_shape_from(&bytes[..]).unwrap();
+        assert_eq!(rebuilt.to_shape_string(), text);
+    }
+
+    #[test]
+    fn broken_shapes() {
+        let read = |text: &str| {
+            Tree::<i64, i64>::from_shape_str(text, Mode::Unbalanced)
+                .err()
+                .unwrap()
+        };
+
+        assert!(matches!(read("1=1 ."), FormatError::BadShape));
+        assert!(matches!(read("1=1 . . ."), FormatError::BadShape));
+        assert!(matches!(read("1=1 x . ."), FormatError::BadShape));
+        assert!(matches!(read("1=x . ."), FormatError::BadRecord));
+        assert!(matches!(read("1=1=1 . ."), FormatError::BadShape));
+        assert!(matches!(read("1=1\\ . ."), FormatError::BadShape));
+        assert!(matches!(read("1=\\u{zz} . ."), FormatError::BadShape));
+        assert!(matches!(
+            read("5=5 1=1 . 7=7 . . ."),
+            FormatError::Unordered
+        ));
+        assert!(matches!(read("5=5 5=5 . . ."), FormatError::Unordered));
+
+        let mut bytes = vec![];
+        Tree::<i64, i64>::from_shape_str("1=1 . .", Mode::Unbalanced)
+            .unwrap()
+            .write_shape_to(&mut bytes)
+            .unwrap();
+        bytes.push(0);
+        let err = Tree::<i64, i64>::read_shape_from(&bytes[..]).err().unwrap();
+        assert!(matches!(err, FormatError::BadShape));
+        bytes.truncate(bytes.len() - 2);
+        let err = Tree::<i64, i64>::read_shape_from(&bytes[..]).err().unwrap();
+        assert!(matches!(err, FormatError::Io(_)));
+    }
+}
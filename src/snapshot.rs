@@ -0,0 +1,465 @@
+use std::cell::{Cell, RefCell};
+use std::cmp::{Ord, Ordering};
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+
+use crate::{Link, Tree, TreeNode};
+
+/// Source of generations, unique across all trees.
+/// Nodes move between trees on split and append, so per tree counters could clash.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn fresh_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, AtomicOrdering::Relaxed)
+}
+
+/// Clones key and value of node, all references stay the same.
+type CopyFn<K, V> = fn(&TreeNode<K, V>) -> TreeNode<K, V>;
+
+/// Copy-on-write bookkeeping of a tree.
+///
+/// Taking a snapshot starts new generation of tree. Nodes of older generations
+/// may be seen by snapshots, so before changing their key, value or children tree copies them
+/// (and their ancestors, as their children change too).
+/// Parent links and subtree sizes are never read by snapshots, so they are changed in place.
+pub(crate) struct CowState<K: Ord, V> {
+    /// Nodes of this generation belong to tree only.
+    generation: Cell<u64>,
+    /// Tokens of snapshots that may share nodes with tree.
+    snapshots: RefCell<Vec<Weak<()>>>,
+    /// Set by the first snapshot, when we know that nodes can be cloned.
+    copy_node: Cell<Option<CopyFn<K, V>>>,
+    /// Whether dead snapshots were dropped from the list during current change.
+    pruned: Cell<bool>,
+}
+
+impl<K: Ord, V> CowState<K, V> {
+    pub(crate) fn new() -> Self {
+        CowState {
+            generation: Cell::new(fresh_generation()),
+            snapshots: RefCell::new(vec![]),
+            copy_node: Cell::new(None),
+            pruned: Cell::new(false),
+        }
+    }
+
+    /// Returns generation new nodes should get.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.get()
+    }
+
+    /// State for a tree that receives some of this tree nodes.
+    pub(crate) fn share(&self) -> Self {
+        CowState {
+            generation: Cell::new(fresh_generation()),
+            snapshots: RefCell::new(self.snapshots.borrow().clone()),
+            copy_node: Cell::new(self.copy_node.get()),
+            pruned: Cell::new(false),
+        }
+    }
+
+    /// Takes into account snapshots of a tree whose nodes were moved into this one.
+    pub(crate) fn merge(&self, other: &Self) {
+        self.snapshots
+            .borrow_mut()
+            .extend(other.snapshots.borrow().iter().cloned());
+        if self.copy_node.get().is_none() {
+            self.copy_node.set(other.copy_node.get());
+        }
+    }
+
+    /// Checks if node may be seen by any living snapshot.
+    /// Dead snapshots are dropped once per change, not for every node it touches.
+    pub(crate) fn is_shared(&self, node: &TreeNode<K, V>) -> bool {
+        if node.generation == self.generation.get() {
+            return false;
+        }
+
+        let mut snapshots = self.snapshots.borrow_mut();
+        if !self.pruned.replace(true) {
+            snapshots.retain(|token| token.strong_count() > 0);
+        }
+        !snapshots.is_empty()
+    }
+
+    /// Lets next change look for dead snapshots again.
+    pub(crate) fn end_change(&self) {
+        self.pruned.set(false);
+    }
+}
+
+/// Follows node to its latest copy.
+/// Handles kept across changes of tree may point to nodes that were replaced.
+pub(crate) fn resolve<K: Ord, V>(
+    node: &Rc<RefCell<TreeNode<K, V>>>,
+) -> Rc<RefCell<TreeNode<K, V>>> {
+    let mut current = Rc::clone(node);
+    loop {
+        let moved = current.borrow().moved.as_ref().and_then(Weak::upgrade);
+        match moved {
+            Some(moved) => current = moved,
+            None => return current,
+        }
+    }
+}
+
+fn copy_node<K: Ord + Clone, V: Clone>(node: &TreeNode<K, V>) -> TreeNode<K, V> {
+    TreeNode {
+        key: node.key.clone(),
+        value: node.value.clone(),
+        count: node.count,
+        generation: node.generation,
+        moved: None,
+        parent: node.parent.clone(),
+        left: node.left.clone(),
+        right: node.right.clone(),
+    }
+}
+
+impl<K: Ord, V> Tree<K, V> {
+    /// Returns read only view of tree as it is now, in O(1).
+    /// Later changes of tree copy nodes they touch, so snapshot never sees them.
+    ///
+    /// Node handles, like ones from `find_node`, point to nodes snapshots may share.
+    /// Values changed through them are not copied, so snapshots see those changes too.
+    pub fn snapshot(&self) -> TreeSnapshot<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let token = Rc::new(());
+        self.cow.snapshots.borrow_mut().push(Rc::downgrade(&token));
+        self.cow.copy_node.set(Some(copy_node::<K, V>));
+        self.cow.generation.set(fresh_generation());
+
+        TreeSnapshot {
+            size: self.size,
+            root: self.root.clone(),
+            _token: token,
+        }
+    }
+
+    /// Makes node safe to change.
+    /// If it is shared with snapshots, it is replaced in tree by a copy, along with its ancestors.
+    /// Returns node to change, it is the given one if there was no need to copy.
+    pub(crate) fn own(
+        &mut self,
+        node: &Rc<RefCell<TreeNode<K, V>>>,
+    ) -> Rc<RefCell<TreeNode<K, V>>> {
+        let node = resolve(node);
+        if !self.cow.is_shared(&node.borrow()) {
+            return node;
+        }
+
+        let parent = node.borrow().parent.clone();
+        let parent = parent.map(|parent| self.own(&parent));
+
+        let copy_node = self.cow.copy_node.get().unwrap();
+        let mut copy = copy_node(&node.borrow());
+        copy.generation = self.cow.generation();
+        copy.parent = parent.clone();
+        let copy = Rc::new(RefCell::new(copy));
+        node.borrow_mut().moved = Some(Rc::downgrade(&copy));
+
+        for child in copy.borrow().left.iter().chain(copy.borrow().right.iter()) {
+            child.borrow_mut().parent = Some(Rc::clone(&copy));
+        }
+
+        match &parent {
+            None => self.root = Some(Rc::clone(&copy)),
+            Some(parent) => {
+                let mut parent = parent.borrow_mut();
+                let is_left = match &parent.left {
+                    Some(left) => Rc::ptr_eq(left, &node),
+                    None => false,
+                };
+                if is_left {
+                    parent.left = Some(Rc::clone(&copy));
+                } else {
+                    parent.right = Some(Rc::clone(&copy));
+                }
+            }
+        }
+        copy
+    }
+}
+
+/// Point-in-time read only view of `Tree`, see `Tree::snapshot`.
+/// Values are cloned out, as nodes are still shared with tree.
+pub struct TreeSnapshot<K: Ord, V> {
+    size: usize,
+    root: Link<K, V>,
+    /// Keeps tree copying nodes while snapshot is alive.
+    _token: Rc<()>,
+}
+
+impl<K: Ord + Clone, V: Clone> TreeSnapshot<K, V> {
+    /// Returns number of nodes at the moment of snapshot.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Checks if there were no nodes at the moment of snapshot.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Returns copy of value stored by given key.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut current = self.root.clone();
+        while let Some(node_ref) = current {
+            let node = node_ref.borrow();
+            current = match key.cmp(&node.key) {
+                Ordering::Less => node.left.clone(),
+                Ordering::Greater => node.right.clone(),
+                Ordering::Equal => return Some(node.value.clone()),
+            };
+        }
+        None
+    }
+
+    /// Checks if there was given key at the moment of snapshot.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns iterator over copies of entries from least to greatest key.
+    pub fn iter(&self) -> SnapshotIter<K, V> {
+        let mut iter = SnapshotIter { stack: vec![] };
+        iter.push_left(self.root.clone());
+        iter
+    }
+}
+
+/// Iterator over snapshot entries.
+/// Parent links belong to tree, so it keeps path in stack.
+pub struct SnapshotIter<K: Ord, V> {
+    stack: Vec<Rc<RefCell<TreeNode<K, V>>>>,
+}
+
+impl<K: Ord, V> SnapshotIter<K, V> {
+    fn push_left(&mut self, mut node: Link<K, V>) {
+        while let Some(lq) = node {
+            node = lq.borrow().left.clone();
+            self.stack.push(lq);
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Iterator for SnapshotIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_ref = self.stack.pop()?;
+        let node = node_ref.borrow();
+        self.push_left(node.right.clone());
+        Some((node.key.clone(), node.value.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{keys, paper_tree, PAPER};
+    use crate::{Mode, Tree, TreeSnapshot};
+    use std::rc::Rc;
+
+    fn paper_entries() -> Vec<(i64, i64)> {
+        let mut entries: Vec<(i64, i64)> = PAPER.iter().map(|i| (*i, *i)).collect();
+        entries.sort();
+        entries
+    }
+
+    fn assert_paper(snapshot: &TreeSnapshot<i64, i64>) {
+        assert_eq!(snapshot.len(), 9);
+        assert_eq!(snapshot.iter().collect::<Vec<_>>(), paper_entries());
+    }
+
+    #[test]
+    fn snapshot_ignores_inserts_and_deletes() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let snapshot = tree.snapshot();
+
+        tree.insert(55, 55);
+        tree.insert(100, 1000);
+        tree.delete(&50);
+        tree.delete(&200);
+        tree.delete(&300);
+
+        assert_paper(&snapshot);
+        assert_eq!(snapshot.get(&100), Some(100));
+        assert!(!snapshot.contains_key(&55));
+
+        assert_eq!(tree.get(&100), Some(1000));
+        assert_eq!(tree.len(), 7);
+        assert_eq!(keys(tree), vec![10, 55, 60, 70, 99, 100, 115]);
+    }
+
+    #[test]
+    fn snapshot_ignores_every_delete() {
+        for deleted in PAPER.iter() {
+            let mut tree = paper_tree(Mode::Unbalanced);
+            let snapshot = tree.snapshot();
+
+            tree.delete(deleted);
+            assert_paper(&snapshot);
+            assert_eq!(tree.len(), 8);
+            assert!(!keys(tree).contains(deleted));
+        }
+    }
+
+    #[test]
+    fn snapshot_ignores_splaying() {
+        let mut tree = paper_tree(Mode::Splay);
+        let snapshot = tree.snapshot();
+
+        tree.splay_find_node(&60);
+        tree.insert(61, 61);
+        assert_eq!(*tree.splay_get(&10).unwrap(), 10);
+
+        assert_paper(&snapshot);
+        assert_eq!(tree.len(), 10);
+    }
+
+    #[test]
+    fn snapshot_ignores_bulk_changes() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let snapshot = tree.snapshot();
+
+        let mut other = tree.split_off(&99);
+        other.insert(400, 400);
+        other.retain(|k, v| {
+            *v += 1;
+            *k != 200
+        });
+        tree.append(&mut other);
+
+        {
+            let mut cursor = tree.lower_bound_mut(&60);
+            cursor.remove_current();
+            *cursor.value_mut().unwrap() = 0;
+            cursor.insert_after(71, 71).unwrap();
+        }
+
+        assert_paper(&snapshot);
+        assert_eq!(tree.get(&70), Some(0));
+        assert_eq!(tree.get(&115), Some(116));
+        assert_eq!(keys(tree), vec![10, 50, 70, 71, 99, 100, 115, 300, 400]);
+    }
+
+    #[test]
+    fn many_snapshots() {
+        let mut tree = Tree::new();
+        let mut snapshots = vec![];
+        for i in 0..20 {
+            snapshots.push(tree.snapshot());
+            tree.insert((i * 7) % 20, i);
+            if i % 3 == 0 {
+                tree.delete(&((i * 11) % 20));
+            }
+        }
+
+        let mut replay = Tree::new();
+        for (i, snapshot) in snapshots.iter().enumerate() {
+            let expected: Vec<(i64, i64)> = keys_values(&replay);
+            assert_eq!(snapshot.iter().collect::<Vec<_>>(), expected);
+            assert_eq!(snapshot.len(), expected.len());
+
+            let i = i as i64;
+            replay.insert((i * 7) % 20, i);
+            if i % 3 == 0 {
+                replay.delete(&((i * 11) % 20));
+            }
+        }
+    }
+
+    fn keys_values(tree: &Tree<i64, i64>) -> Vec<(i64, i64)> {
+        let mut entries = vec![];
+        let mut cursor = tree.lower_bound(&i64::MIN);
+        while let Some(node) = cursor.node() {
+            entries.push((node.borrow().key, node.borrow().value));
+            cursor.move_next();
+        }
+        entries
+    }
+
+    #[test]
+    fn no_copies_without_snapshots() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        drop(tree.snapshot());
+
+        let root = Rc::clone(tree.root.as_ref().unwrap());
+        tree.insert(1, 1);
+        tree.delete(&10);
+        assert!(Rc::ptr_eq(&root, tree.root.as_ref().unwrap()));
+
+        let _snapshot = tree.snapshot();
+        tree.insert(2, 2);
+        assert!(!Rc::ptr_eq(&root, tree.root.as_ref().unwrap()));
+    }
+
+    #[test]
+    fn dead_snapshots_pruned_on_change() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let kept = tree.snapshot();
+        for _ in 0..5 {
+            drop(tree.snapshot());
+        }
+        assert_eq!(tree.cow.snapshots.borrow().len(), 6);
+        tree.insert(1, 1);
+        assert_eq!(tree.cow.snapshots.borrow().len(), 1);
+
+        // Handles bypass copy-on-write
+        tree.find_node(&200).unwrap().borrow_mut().value = 2;
+        assert_eq!(kept.get(&200), Some(2));
+        assert_eq!(kept.get(&1), None);
+    }
+
+    #[test]
+    fn random_changes_against_model() {
+        use std::collections::BTreeMap;
+
+        for mode in &[Mode::Unbalanced, Mode::Splay] {
+            let mut seed: u64 = 42;
+            let mut next = move || {
+                seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+                (seed >> 33) as i64
+            };
+
+            let mut tree = Tree::with_mode(*mode);
+            let mut model = BTreeMap::new();
+            let mut snapshots = vec![];
+
+            for step in 0..2000 {
+                let key = next() % 64;
+                match next() % 4 {
+                    0 => {
+                        tree.delete(&key);
+                        model.remove(&key);
+                    }
+                    1 if *mode == Mode::Splay => {
+                        tree.splay_find_node(&key);
+                    }
+                    _ => {
+                        assert_eq!(tree.insert(key, step), model.insert(key, step));
+                    }
+                }
+
+                if step % 50 == 0 {
+                    let expected: Vec<(i64, i64)> = model.iter().map(|(k, v)| (*k, *v)).collect();
+                    snapshots.push((tree.snapshot(), expected));
+                }
+                if step % 170 == 0 {
+                    // Some snapshots die, copying should still be right
+                    snapshots.retain(|_| next() % 2 == 0);
+                }
+            }
+
+            assert_eq!(tree.len(), model.len());
+            assert_eq!(keys_values(&tree), model.into_iter().collect::<Vec<_>>());
+            for (snapshot, expected) in snapshots.iter() {
+                assert_eq!(snapshot.iter().collect::<Vec<_>>(), *expected);
+                assert_eq!(snapshot.len(), expected.len());
+            }
+        }
+    }
+}
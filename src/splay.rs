@@ -0,0 +1,195 @@
+use std::cell::{Ref, RefCell};
+use std::cmp::{Ord, Ordering};
+use std::rc::Rc;
+
+use crate::{Tree, TreeNode};
+
+/// Splaying: rotations that pull accessed node up to the root.
+/// Rotations work in any mode, but only splay mode calls them on its own.
+impl<K: Ord, V> Tree<K, V> {
+    /// Tries to find node by given key and moves it to the root.
+    /// If key is missing, last visited node is moved instead, as splay trees do.
+    pub fn splay_find_node(&mut self, f: &K) -> Option<Rc<RefCell<TreeNode<K, V>>>> {
+        let last = self.find_node_or_last(f)?;
+        self.splay(&last);
+        self.cow.end_change();
+
+        // Splayed node might have been copied for snapshots, root is the right one
+        let root = Rc::clone(self.root.as_ref().unwrap());
+        let found = root.borrow().key.cmp(f) == Ordering::Equal;
+        if found {
+            Some(root)
+        } else {
+            None
+        }
+    }
+
+    /// Returns value stored by given key and moves its node to the root.
+    /// Value is borrowed right from the root, so no cloning needed.
+    pub fn splay_get(&mut self, key: &K) -> Option<Ref<'_, V>> {
+        self.splay_find_node(key)?;
+
+        let root = self.root.as_ref().unwrap();
+        Some(Ref::map(root.borrow(), |node| &node.value))
+    }
+
+    /// Moves given node to the root with zig, zig-zig and zig-zag steps.
+    pub(crate) fn splay(&mut self, x: &Rc<RefCell<TreeNode<K, V>>>) {
+        // Owning node owns its whole path, so rotations below never copy anything
+        let x = &self.own(x);
+        loop {
+            let parent = x.borrow().parent.clone();
+            let parent = match parent {
+                None => break,
+                Some(parent) => parent,
+            };
+            let grand = parent.borrow().parent.clone();
+
+            match grand {
+                // zig
+                None => self.rotate_up(x),
+                Some(grand) => {
+                    if is_left_child(x, &parent) == is_left_child(&parent, &grand) {
+                        // zig-zig
+                        self.rotate_up(&parent);
+                        self.rotate_up(x);
+                    } else {
+                        // zig-zag
+                        self.rotate_up(x);
+                        self.rotate_up(x);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Rotates node above its parent keeping all parent links right.
+    /// Node must have a parent.
+    pub(crate) fn rotate_up(&mut self, x: &Rc<RefCell<TreeNode<K, V>>>) {
+        let x = &self.own(x);
+        let p = x.borrow().parent_sure();
+        let g = p.borrow().parent.clone();
+
+        if is_left_child(x, &p) {
+            // x.right goes to p.left
+            let moved = x.borrow_mut().right.take();
+            if let Some(moved) = &moved {
+                moved.borrow_mut().parent = Some(Rc::clone(&p));
+            }
+            p.borrow_mut().left = moved;
+            x.borrow_mut().right = Some(Rc::clone(&p));
+        } else {
+            // x.left goes to p.right
+            let moved = x.borrow_mut().left.take();
+            if let Some(moved) = &moved {
+                moved.borrow_mut().parent = Some(Rc::clone(&p));
+            }
+            p.borrow_mut().right = moved;
+            x.borrow_mut().left = Some(Rc::clone(&p));
+        }
+        p.borrow_mut().parent = Some(Rc::clone(x));
+        p.borrow_mut().recount();
+        x.borrow_mut().recount();
+
+        match &g {
+            None => self.root = Some(Rc::clone(x)),
+            Some(g) => {
+                if is_left_child(&p, g) {
+                    g.borrow_mut().left = Some(Rc::clone(x));
+                } else {
+                    g.borrow_mut().right = Some(Rc::clone(x));
+                }
+            }
+        }
+        x.borrow_mut().parent = g;
+    }
+}
+
+/// Checks if node hangs on the left side of given parent.
+fn is_left_child<K: Ord, V>(
+    node: &Rc<RefCell<TreeNode<K, V>>>,
+    parent: &Rc<RefCell<TreeNode<K, V>>>,
+) -> bool {
+    match &parent.borrow().left {
+        Some(left) => Rc::ptr_eq(left, node),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Mode, Tree};
+    use std::rc::Rc;
+
+    #[test]
+    fn insert_moves_to_root() {
+        let mut tree: Tree<i64, i64> = Tree::with_mode(Mode::Splay);
+        for i in &[50, 10, 70, 60, 99, 5] {
+            tree.insert(*i, *i);
+            assert_eq!(tree.root.as_ref().unwrap().borrow().key, *i);
+        }
+
+        assert_eq!(tree.len(), 6);
+        assert_eq!(tree.into_iter().count(), 6);
+    }
+
+    #[test]
+    fn find_moves_to_root() {
+        let mut tree: Tree<i64, i64> = Tree::with_mode(Mode::Splay);
+        for i in 0..100 {
+            tree.insert(i, i * 10);
+        }
+
+        for i in &[0, 99, 42, 43, 17, 42] {
+            let node = tree.splay_find_node(i).unwrap();
+            assert!(Rc::ptr_eq(&node, tree.root.as_ref().unwrap()));
+            assert!(node.borrow().parent.is_none());
+        }
+
+        assert_eq!(*tree.splay_get(&7).unwrap(), 70);
+        assert_eq!(tree.root.as_ref().unwrap().borrow().key, 7);
+
+        let keys: Vec<i64> = tree.into_iter().map(|n| n.borrow().key).collect();
+        assert_eq!(keys, (0..100).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn find_miss_splays_last() {
+        let mut tree: Tree<i64, i64> = Tree::with_mode(Mode::Splay);
+        for i in &[10, 20, 30] {
+            tree.insert(*i, *i);
+        }
+
+        assert!(tree.splay_find_node(&25).is_none());
+        let root_key = tree.root.as_ref().unwrap().borrow().key;
+        assert!(root_key == 20 || root_key == 30);
+        assert!(tree.splay_get(&25).is_none());
+        assert_eq!(tree.len(), 3);
+    }
+
+    #[test]
+    fn find_node_does_not_splay() {
+        let mut tree: Tree<i64, i64> = Tree::with_mode(Mode::Splay);
+        for i in 0..10 {
+            tree.insert(i, i);
+        }
+
+        assert_eq!(tree.get(&3), Some(3));
+        assert!(tree.find_node(&0).is_some());
+        assert_eq!(tree.root.as_ref().unwrap().borrow().key, 9);
+    }
+
+    #[test]
+    fn delete_after_splay() {
+        let mut tree: Tree<i64, i64> = Tree::with_mode(Mode::Splay);
+        for i in &[5, 3, 8, 1, 4, 7, 9] {
+            tree.insert(*i, *i);
+        }
+        tree.splay_find_node(&4);
+
+        assert!(tree.delete(&4).is_some());
+        assert!(tree.delete(&8).is_some());
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.into_iter().count(), 5);
+    }
+}
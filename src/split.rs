@@ -0,0 +1,274 @@
+use std::cmp::{Ord, Ordering};
+use std::rc::Rc;
+
+use crate::{count_of, Link, Mode, Tree};
+
+/// Cutting tree in two by key and gluing two trees back.
+/// Unbalanced tree does it in O(height), splay tree in amortized O(log n).
+impl<K: Ord, V> Tree<K, V> {
+    /// Moves all keys greater or equal to given one into returned tree.
+    /// Returned tree has the same mode.
+    pub fn split_off(&mut self, key: &K) -> Self {
+        let mut other = Tree::with_mode(self.mode);
+        if self.root.is_none() {
+            return other;
+        }
+        other.cow = self.cow.share();
+
+        let (less, rest) = match self.mode {
+            Mode::Unbalanced => {
+                // Cut goes along the search path, owning its end owns all of it
+                let last = self.find_node_or_last(key).unwrap();
+                self.own(&last);
+                split_r(self.root.take(), key)
+            }
+            Mode::Splay => {
+                // After splaying root is the closest key, so cut goes right next to it
+                let last = self.find_node_or_last(key).unwrap();
+                self.splay(&last);
+
+                let root_ref = self.root.take().unwrap();
+                let mut root = root_ref.borrow_mut();
+                if root.key.cmp(key) == Ordering::Less {
+                    let rest = root.right.take();
+                    root.recount();
+                    drop(root);
+                    (Some(root_ref), rest)
+                } else {
+                    let less = root.left.take();
+                    root.recount();
+                    drop(root);
+                    (less, Some(root_ref))
+                }
+            }
+        };
+
+        for root in less.iter().chain(rest.iter()) {
+            root.borrow_mut().parent = None;
+        }
+
+        self.size = count_of(&less);
+        self.root = less;
+        other.size = count_of(&rest);
+        other.root = rest;
+
+        for node in other.nodes_if(!self.observers.is_empty()) {
+            self.notify_removed(&node);
+        }
+        self.finish_change();
+        other
+    }
+
+    /// Moves all nodes of other tree into this one, leaving other empty.
+    /// All keys of one tree must be less than all keys of another,
+    /// it does not matter which one is on the left.
+    ///
+    /// Panics if key ranges of trees overlap.
+    pub fn append(&mut self, other: &mut Self) {
+        // Moved nodes are reported once they are in place
+        let moved = other.nodes_if(!self.observers.is_empty() || !other.observers.is_empty());
+        self.append_nodes(other);
+
+        for node in moved {
+            other.notify_removed(&node);
+            self.notify_inserted(&node);
+        }
+        other.finish_change();
+        self.finish_change();
+    }
+
+    fn append_nodes(&mut self, other: &mut Self) {
+        if other.root.is_none() {
+            return;
+        }
+        self.cow.merge(&other.cow);
+        if self.root.is_none() {
+            std::mem::swap(&mut self.root, &mut other.root);
+            std::mem::swap(&mut self.size, &mut other.size);
+            return;
+        }
+
+        let size = self.size + other.size;
+        let (left, right) = if is_before(self, other) {
+            (self.root.take().unwrap(), other.root.take().unwrap())
+        } else if is_before(other, self) {
+            (other.root.take().unwrap(), self.root.take().unwrap())
+        } else {
+            panic!("Can not append tree with overlapping keys");
+        };
+
+        // Right tree hangs on the greatest node of left one
+        self.root = Some(left);
+        let greatest = self.greatest_node().unwrap();
+        if self.mode == Mode::Splay {
+            self.splay(&greatest);
+        }
+        let greatest = self.own(&greatest);
+
+        right.borrow_mut().parent = Some(Rc::clone(&greatest));
+        greatest.borrow_mut().right = Some(right);
+        self.recount_up(Some(greatest));
+
+        self.size = size;
+        other.size = 0;
+    }
+
+    /// Glues two trees into one, keeping mode of left one.
+    ///
+    /// Panics if key ranges of trees overlap.
+    pub fn join(left: Self, right: Self) -> Self {
+        let mut left = left;
+        let mut right = right;
+        left.append(&mut right);
+        left
+    }
+}
+
+/// Checks if all keys of first tree are less than keys of second.
+/// Both trees must not be empty.
+fn is_before<K: Ord, V>(first: &Tree<K, V>, second: &Tree<K, V>) -> bool {
+    let greatest = first.greatest_node().unwrap();
+    let least = second.least_node().unwrap();
+    let is_before = greatest.borrow().key.cmp(&least.borrow().key) == Ordering::Less;
+    is_before
+}
+
+/// Recursively cuts subtree into nodes less than key and the rest.
+/// Returned roots still have their old parent links, caller fixes them.
+fn split_r<K: Ord, V>(link: Link<K, V>, key: &K) -> (Link<K, V>, Link<K, V>) {
+    let node_ref = match link {
+        None => return (None, None),
+        Some(node_ref) => node_ref,
+    };
+    let mut node = node_ref.borrow_mut();
+
+    if node.key.cmp(key) == Ordering::Less {
+        // Node and its left side stay, right side is cut further
+        let (less, rest) = split_r(node.right.take(), key);
+        if let Some(less) = &less {
+            less.borrow_mut().parent = Some(Rc::clone(&node_ref));
+        }
+        node.right = less;
+        node.recount();
+        drop(node);
+        (Some(node_ref), rest)
+    } else {
+        // Node and its right side go away, left side is cut further
+        let (less, rest) = split_r(node.left.take(), key);
+        if let Some(rest) = &rest {
+            rest.borrow_mut().parent = Some(Rc::clone(&node_ref));
+        }
+        node.left = rest;
+        node.recount();
+        drop(node);
+        (less, Some(node_ref))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{keys, paper_tree};
+    use crate::{Mode, Tree};
+
+    #[test]
+    fn split_every_key() {
+        for mode in &[Mode::Unbalanced, Mode::Splay] {
+            for at in &[0, 10, 55, 60, 100, 101, 300, 400] {
+                let mut tree = paper_tree(*mode);
+                let other = tree.split_off(at);
+                assert_eq!(tree.validate(), Ok(()));
+                assert_eq!(other.validate(), Ok(()));
+
+                assert_eq!(other.mode(), *mode);
+                assert!(keys(tree).iter().all(|k| k < at));
+                assert!(keys(other).iter().all(|k| k >= at));
+            }
+        }
+    }
+
+    #[test]
+    fn split_sizes() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let other = tree.split_off(&99);
+        assert_eq!(tree.len(), 4);
+        assert_eq!(other.len(), 5);
+
+        let mut empty: Tree<i64, i64> = Tree::new();
+        assert!(empty.split_off(&1).is_empty());
+    }
+
+    #[test]
+    fn split_then_append() {
+        for mode in &[Mode::Unbalanced, Mode::Splay] {
+            let mut tree = paper_tree(*mode);
+            let mut other = tree.split_off(&70);
+            tree.append(&mut other);
+
+            assert_eq!(tree.validate(), Ok(()));
+            assert!(other.is_empty());
+            assert_eq!(keys(tree), vec![10, 50, 60, 70, 99, 100, 115, 200, 300]);
+        }
+    }
+
+    #[test]
+    fn append_smaller() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let mut other = Tree::new();
+        other.insert(-5, -5);
+        other.insert(-10, -10);
+
+        tree.append(&mut other);
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.len(), 11);
+        assert_eq!(keys(tree)[..3], [-10, -5, 10]);
+    }
+
+    #[test]
+    fn append_empty() {
+        let mut tree: Tree<i64, i64> = Tree::new();
+        let mut other = paper_tree(Mode::Unbalanced);
+        tree.append(&mut other);
+        assert_eq!(tree.len(), 9);
+        assert!(other.is_empty());
+
+        tree.append(&mut other);
+        assert_eq!(tree.len(), 9);
+    }
+
+    #[test]
+    fn join_trees() {
+        let mut left = Tree::with_mode(Mode::Splay);
+        let mut right = Tree::new();
+        for i in 0..10 {
+            left.insert(i, i);
+            right.insert(i + 10, i);
+        }
+
+        let joined = Tree::join(left, right);
+        assert_eq!(joined.validate(), Ok(()));
+        assert_eq!(joined.mode(), Mode::Splay);
+        assert_eq!(keys(joined), (0..20).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    #[should_panic]
+    fn append_overlapping() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let mut other = paper_tree(Mode::Unbalanced);
+        tree.append(&mut other);
+    }
+
+    #[test]
+    fn counts_after_delete() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        for i in &[50, 100, 115, 10] {
+            tree.delete(i);
+            assert_eq!(tree.validate(), Ok(()));
+        }
+
+        let mut splayed = paper_tree(Mode::Splay);
+        splayed.splay_find_node(&60);
+        splayed.delete(&200);
+        assert_eq!(splayed.validate(), Ok(()));
+    }
+}
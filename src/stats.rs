@@ -0,0 +1,137 @@
+use std::cell::RefCell;
+use std::cmp::Ord;
+use std::mem;
+
+use crate::{Tree, TreeNode};
+
+/// Shape of tree, see `Tree::stats`. Depth of root is 0.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeStats {
+    pub size: usize,
+    /// Number of levels, 0 for empty tree.
+    pub height: usize,
+    pub min_leaf_depth: usize,
+    pub max_leaf_depth: usize,
+    pub avg_leaf_depth: f64,
+    /// Number of nodes at every depth.
+    pub depth_histogram: Vec<usize>,
+    pub leaves: usize,
+    pub one_child: usize,
+    pub two_children: usize,
+    /// Height divided by the least possible height for this size, 1.0 is perfect.
+    pub imbalance: f64,
+    /// Heap taken by nodes. Memory owned by keys and values themselves is not counted.
+    pub heap_bytes: usize,
+}
+
+impl<K: Ord, V> Tree<K, V> {
+    /// Walks whole tree and measures its shape.
+    pub fn stats(&self) -> TreeStats {
+        let mut histogram: Vec<usize> = vec![];
+        let mut leaf_depths = (usize::MAX, 0, 0);
+        let (mut leaves, mut one_child, mut two_children) = (0, 0, 0);
+
+        let mut stack: Vec<_> = self.root.iter().map(|root| (root.clone(), 0)).collect();
+        while let Some((node, depth)) = stack.pop() {
+            if histogram.len() == depth {
+                histogram.push(0);
+            }
+            histogram[depth] += 1;
+
+            let node = node.borrow();
+            let children: Vec<_> = node.left.iter().chain(node.right.iter()).collect();
+            match children.len() {
+                0 => {
+                    leaves += 1;
+                    let (min, max, sum) = leaf_depths;
+                    leaf_depths = (min.min(depth), max.max(depth), sum + depth);
+                }
+                1 => one_child += 1,
+                _ => two_children += 1,
+            }
+            for child in children {
+                stack.push((child.clone(), depth + 1));
+            }
+        }
+
+        let (min_leaf_depth, max_leaf_depth, depth_sum) = match leaves {
+            0 => (0, 0, 0),
+            _ => leaf_depths,
+        };
+        let height = histogram.len();
+        // Perfect tree of this size has ceil(log2(size + 1)) levels
+        let optimal = (usize::BITS - self.size.leading_zeros()) as usize;
+
+        TreeStats {
+            size: self.size,
+            height,
+            min_leaf_depth,
+            max_leaf_depth,
+            avg_leaf_depth: if leaves == 0 {
+                0.0
+            } else {
+                depth_sum as f64 / leaves as f64
+            },
+            depth_histogram: histogram,
+            leaves,
+            one_child,
+            two_children,
+            imbalance: if optimal == 0 {
+                1.0
+            } else {
+                height as f64 / optimal as f64
+            },
+            // Rc allocation holds strong and weak counters next to the value
+            heap_bytes: self.size
+                * (2 * mem::size_of::<usize>() + mem::size_of::<RefCell<TreeNode<K, V>>>()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Tree;
+
+    #[test]
+    fn paper_tree() {
+        let mut tree = Tree::new();
+        for key in &[100, 50, 10, 70, 60, 99, 200, 300] {
+            tree.insert(*key, *key);
+        }
+
+        let stats = tree.stats();
+        assert_eq!(stats.size, 8);
+        assert_eq!(stats.height, 4);
+        assert_eq!(stats.depth_histogram, vec![1, 2, 3, 2]);
+        assert_eq!((stats.min_leaf_depth, stats.max_leaf_depth), (2, 3));
+        assert_eq!(stats.avg_leaf_depth, 10.0 / 4.0);
+        assert_eq!(
+            (stats.leaves, stats.one_child, stats.two_children),
+            (4, 1, 3)
+        );
+        assert_eq!(stats.imbalance, 1.0);
+        assert!(stats.heap_bytes >= 8 * 2 * std::mem::size_of::<i32>());
+    }
+
+    #[test]
+    fn degenerate_and_empty() {
+        let mut tree = Tree::new();
+        for key in 0..15 {
+            tree.insert(key, ());
+        }
+        let stats = tree.stats();
+        assert_eq!(stats.height, 15);
+        assert_eq!(stats.depth_histogram, vec![1; 15]);
+        assert_eq!(
+            (stats.leaves, stats.one_child, stats.two_children),
+            (1, 14, 0)
+        );
+        assert_eq!(stats.imbalance, 15.0 / 4.0);
+
+        let stats = Tree::<i64, i64>::new().stats();
+        assert_eq!(stats.height, 0);
+        assert_eq!(stats.avg_leaf_depth, 0.0);
+        assert_eq!(stats.imbalance, 1.0);
+        assert_eq!(stats.heap_bytes, 0);
+    }
+}
@@ -0,0 +1,444 @@
+use std::cmp::{Ord, Ordering};
+use std::ops::{Bound, RangeBounds};
+use std::sync::RwLock;
+
+use crate::optimistic::CommitLog;
+
+/// Thread safe counterpart of `Tree`.
+/// Same unbalanced tree, but nodes are owned boxes without parent links,
+/// and whole tree sits behind a `RwLock`: many readers or one writer at a time.
+/// Share it between threads with `Arc<SyncTree<K, V>>`.
+pub struct SyncTree<K: Ord, V> {
+    pub(crate) inner: RwLock<SyncInner<K, V>>,
+}
+
+pub(crate) struct SyncInner<K: Ord, V> {
+    size: usize,
+    root: Option<Box<SyncNode<K, V>>>,
+    /// Writes that running optimistic transactions have to check against.
+    pub(crate) commits: CommitLog<K>,
+}
+
+struct SyncNode<K: Ord, V> {
+    key: K,
+    value: V,
+    left: Option<Box<SyncNode<K, V>>>,
+    right: Option<Box<SyncNode<K, V>>>,
+}
+
+impl<K: Ord, V> SyncTree<K, V> {
+    /// Creates empty tree.
+    pub fn new() -> Self {
+        SyncTree {
+            inner: RwLock::new(SyncInner {
+                size: 0,
+                root: None,
+                commits: CommitLog::new(),
+            }),
+        }
+    }
+
+    /// Inserts key-value into tree.
+    /// Returns optional value of replaced value, if there was any.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let inner = &mut *self.inner.write().unwrap();
+        let replaced = inner.insert(key, value);
+        inner.commits.record_untracked();
+        replaced
+    }
+
+    /// Returns copy of value stored by given key.
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        let inner = self.inner.read().unwrap();
+        find(&inner.root, key).map(|node| node.value.clone())
+    }
+
+    /// Checks if there is given key in tree.
+    pub fn contains_key(&self, key: &K) -> bool {
+        let inner = self.inner.read().unwrap();
+        find(&inner.root, key).is_some()
+    }
+
+    /// Tries to delete node with given key.
+    /// Returns value of deleted node if there was any.
+    pub fn delete(&self, key: &K) -> Option<V> {
+        let inner = &mut *self.inner.write().unwrap();
+        let deleted = inner.delete(key)?;
+        inner.commits.record_untracked();
+        Some(deleted)
+    }
+
+    /// Clears tree.
+    pub fn clear(&self) {
+        let inner = &mut *self.inner.write().unwrap();
+        drop_subtree(inner.root.take());
+        inner.size = 0;
+        inner.commits.record_untracked();
+    }
+
+    /// Returns number of nodes in tree.
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().size
+    }
+
+    /// Checks if tree has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns copies of entries in given range, in key order.
+    pub fn range<R>(&self, range: R) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+        R: RangeBounds<K>,
+    {
+        self.inner.read().unwrap().range(&range)
+    }
+
+    /// Calls given function for every entry from least to greatest key.
+    /// Tree is locked for reading the whole time.
+    pub fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(&K, &V),
+    {
+        let inner = self.inner.read().unwrap();
+
+        let mut stack: Vec<&SyncNode<K, V>> = vec![];
+        let mut current = inner.root.as_deref();
+        loop {
+            while let Some(node) = current {
+                stack.push(node);
+                current = node.left.as_deref();
+            }
+
+            match stack.pop() {
+                None => break,
+                Some(node) => {
+                    f(&node.key, &node.value);
+                    current = node.right.as_deref();
+                }
+            }
+        }
+    }
+}
+
+impl<K: Ord, V> SyncInner<K, V> {
+    /// Inserts key-value into tree.
+    /// Returns optional value of replaced value, if there was any.
+    pub(crate) fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let mut link = &mut self.root;
+        loop {
+            match link.as_ref().map(|node| key.cmp(&node.key)) {
+                None => break,
+                Some(Ordering::Less) => link = &mut link.as_mut().unwrap().left,
+                Some(Ordering::Greater) => link = &mut link.as_mut().unwrap().right,
+                Some(Ordering::Equal) => {
+                    let node = link.as_mut().unwrap();
+                    return Some(std::mem::replace(&mut node.value, value));
+                }
+            }
+        }
+
+        *link = Some(Box::new(SyncNode {
+            key,
+            value,
+            left: None,
+            right: None,
+        }));
+        self.size += 1;
+        None
+    }
+
+    /// Tries to delete node with given key.
+    /// Returns value of deleted node if there was any.
+    pub(crate) fn delete(&mut self, key: &K) -> Option<V> {
+        let mut link = &mut self.root;
+        loop {
+            match link.as_ref().map(|node| key.cmp(&node.key)) {
+                None => return None,
+                Some(Ordering::Less) => link = &mut link.as_mut().unwrap().left,
+                Some(Ordering::Greater) => link = &mut link.as_mut().unwrap().right,
+                Some(Ordering::Equal) => break,
+            }
+        }
+
+        let mut node = link.take().unwrap();
+        *link = match (node.left.take(), node.right.take()) {
+            (None, None) => None,
+            (Some(left), None) => Some(left),
+            (None, Some(right)) => Some(right),
+            // Same lazy trick as in Tree: left subtree goes under least node of right one
+            (Some(left), Some(mut right)) => {
+                let mut least = &mut right;
+                while least.left.is_some() {
+                    least = least.left.as_mut().unwrap();
+                }
+                least.left = Some(left);
+                Some(right)
+            }
+        };
+
+        self.size -= 1;
+        Some(node.value)
+    }
+
+    /// Collects copies of entries in given range, in key order.
+    pub(crate) fn range<R>(&self, range: &R) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+        R: RangeBounds<K>,
+    {
+        let mut entries = vec![];
+        collect_range(self.root.as_deref(), range, &mut entries);
+        entries
+    }
+}
+
+/// In-order walk that skips subtrees out of range.
+/// Iterative, as sorted inserts make tree as deep as it is long.
+fn collect_range<K: Ord + Clone, V: Clone, R: RangeBounds<K>>(
+    root: Option<&SyncNode<K, V>>,
+    range: &R,
+    entries: &mut Vec<(K, V)>,
+) {
+    let mut stack: Vec<&SyncNode<K, V>> = vec![];
+    let mut current = root;
+    loop {
+        while let Some(node) = current {
+            stack.push(node);
+            let go_left = match range.start_bound() {
+                Bound::Included(start) | Bound::Excluded(start) => node.key > *start,
+                Bound::Unbounded => true,
+            };
+            current = if go_left { node.left.as_deref() } else { None };
+        }
+
+        let node = match stack.pop() {
+            None => break,
+            Some(node) => node,
+        };
+        if range.contains(&node.key) {
+            entries.push((node.key.clone(), node.value.clone()));
+        }
+        let go_right = match range.end_bound() {
+            Bound::Included(end) | Bound::Excluded(end) => node.key < *end,
+            Bound::Unbounded => true,
+        };
+        current = if go_right {
+            node.right.as_deref()
+        } else {
+            None
+        };
+    }
+}
+
+/// Drops subtree node by node, default drop of boxes would recurse as deep as tree is.
+fn drop_subtree<K: Ord, V>(root: Option<Box<SyncNode<K, V>>>) {
+    let mut stack: Vec<_> = root.into_iter().collect();
+    while let Some(mut node) = stack.pop() {
+        stack.extend(node.left.take());
+        stack.extend(node.right.take());
+    }
+}
+
+impl<K: Ord, V> Drop for SyncInner<K, V> {
+    fn drop(&mut self) {
+        drop_subtree(self.root.take());
+    }
+}
+
+impl<K: Ord, V> Default for SyncTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterative search for node by given key.
+fn find<'a, K: Ord, V>(
+    root: &'a Option<Box<SyncNode<K, V>>>,
+    key: &K,
+) -> Option<&'a SyncNode<K, V>> {
+    let mut current = root.as_deref();
+    while let Some(node) = current {
+        current = match key.cmp(&node.key) {
+            Ordering::Less => node.left.as_deref(),
+            Ordering::Greater => node.right.as_deref(),
+            Ordering::Equal => return Some(node),
+        };
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyncTree;
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Spreads keys so tree does not turn into a list.
+    fn scramble(i: u64) -> u64 {
+        i.wrapping_mul(2_654_435_761) % 1_000_003
+    }
+
+    fn keys(tree: &SyncTree<u64, u64>) -> Vec<u64> {
+        let mut keys = vec![];
+        tree.for_each(|k, _| keys.push(*k));
+        keys
+    }
+
+    #[test]
+    fn is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SyncTree<u64, String>>();
+    }
+
+    #[test]
+    fn insert_get_delete() {
+        let tree = SyncTree::new();
+        assert_eq!(tree.insert(100, 1), None);
+        assert_eq!(tree.insert(50, 2), None);
+        assert_eq!(tree.insert(200, 3), None);
+        assert_eq!(tree.insert(150, 4), None);
+        assert_eq!(tree.insert(100, 5), Some(1));
+
+        assert_eq!(tree.get(&100), Some(5));
+        assert!(tree.contains_key(&150));
+        assert_eq!(tree.len(), 4);
+
+        assert_eq!(tree.delete(&100), Some(5));
+        assert_eq!(tree.delete(&100), None);
+        assert_eq!(keys(&tree), vec![50, 150, 200]);
+
+        tree.clear();
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn ranges() {
+        let tree = SyncTree::new();
+        for i in &[100, 50, 10, 70, 60, 99, 200, 115, 300] {
+            tree.insert(*i, *i);
+        }
+
+        let keys =
+            |entries: Vec<(u64, u64)>| entries.into_iter().map(|(k, _)| k).collect::<Vec<_>>();
+        assert_eq!(keys(tree.range(60..115)), vec![60, 70, 99, 100]);
+        assert_eq!(keys(tree.range(60..=115)), vec![60, 70, 99, 100, 115]);
+        assert_eq!(keys(tree.range(..=10)), vec![10]);
+        assert_eq!(keys(tree.range(201..)), vec![300]);
+        assert_eq!(tree.range(..).len(), 9);
+        assert!(tree.range(301..).is_empty());
+    }
+
+    #[test]
+    fn deep_tree() {
+        // Sorted inserts make a list, small stack shows walks do not recurse
+        let worker = thread::Builder::new().stack_size(64 * 1024);
+        let handle = worker.spawn(|| {
+            let tree = SyncTree::new();
+            for i in 0..5000u64 {
+                tree.insert(i, i);
+            }
+            assert_eq!(tree.range(10..20).len(), 10);
+            assert_eq!(tree.range(4990..).len(), 10);
+            assert_eq!(tree.range(..).len(), 5000);
+            tree.clear();
+            for i in (0..5000u64).rev() {
+                tree.insert(i, i);
+            }
+            drop(tree);
+        });
+        handle.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn delete_every_shape() {
+        let inserted = [100, 50, 10, 70, 60, 99, 200, 115, 300];
+        for deleted in inserted.iter() {
+            let tree = SyncTree::new();
+            for i in inserted.iter() {
+                tree.insert(*i, *i);
+            }
+
+            assert_eq!(tree.delete(deleted), Some(*deleted));
+            let mut expected: Vec<u64> =
+                inserted.iter().cloned().filter(|i| i != deleted).collect();
+            expected.sort();
+            assert_eq!(keys(&tree), expected);
+            assert_eq!(tree.len(), 8);
+        }
+    }
+
+    #[test]
+    fn hammer_writers() {
+        let tree = Arc::new(SyncTree::new());
+
+        let handles: Vec<_> = (0..8u64)
+            .map(|t| {
+                let tree = Arc::clone(&tree);
+                thread::spawn(move || {
+                    for i in 0..500 {
+                        let key = scramble(t * 500 + i);
+                        assert_eq!(tree.insert(key, t), None);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(tree.len(), 4000);
+        let keys = keys(&tree);
+        assert_eq!(keys.len(), 4000);
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn hammer_readers_and_writers() {
+        let tree = Arc::new(SyncTree::new());
+        for i in 0..1000 {
+            tree.insert(scramble(i), i);
+        }
+
+        let mut handles = vec![];
+        // Writers delete odd keys and insert new ones
+        for t in 0..4u64 {
+            let tree = Arc::clone(&tree);
+            handles.push(thread::spawn(move || {
+                for i in (0..1000).filter(|i| i % 4 == t && i % 2 == 1) {
+                    assert_eq!(tree.delete(&scramble(i)), Some(i));
+                    tree.insert(scramble(1000 + i), i);
+                }
+            }));
+        }
+        // Readers always see even keys and never a torn tree
+        for _ in 0..4 {
+            let tree = Arc::clone(&tree);
+            handles.push(thread::spawn(move || {
+                for _ in 0..20 {
+                    for i in (0..1000).filter(|i| i % 2 == 0) {
+                        assert_eq!(tree.get(&scramble(i)), Some(i));
+                    }
+                    let keys = keys(&tree);
+                    assert!(keys.windows(2).all(|w| w[0] < w[1]));
+                    assert!(keys.len() >= 996 && keys.len() <= 1000);
+                }
+            }));
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(tree.len(), 1000);
+        for i in (0..1000).filter(|i| i % 2 == 1) {
+            assert!(!tree.contains_key(&scramble(i)));
+            assert_eq!(tree.get(&scramble(1000 + i)), Some(i));
+        }
+    }
+}
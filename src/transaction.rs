@@ -0,0 +1,154 @@
+use std::cmp::Ord;
+
+use crate::{Tree, WriteBatch};
+
+impl<K: Ord, V> Tree<K, V> {
+    /// Starts transaction over this tree.
+    /// Changes are buffered in transaction and reach tree only on `commit`.
+    pub fn transaction(&mut self) -> Transaction<'_, K, V> {
+        Transaction {
+            tree: self,
+            pending: Tree::new(),
+        }
+    }
+}
+
+/// All-or-nothing batch of changes, see `Tree::transaction`.
+/// Dropping transaction without commit discards its changes.
+pub struct Transaction<'a, K: Ord, V> {
+    tree: &'a mut Tree<K, V>,
+    /// Buffered writes, None marks deletion.
+    pending: Tree<K, Option<V>>,
+}
+
+impl<'a, K: Ord, V> Transaction<'a, K, V> {
+    /// Buffers insertion of key-value.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.pending.insert(key, Some(value));
+    }
+
+    /// Buffers deletion of key.
+    pub fn delete(&mut self, key: &K)
+    where
+        K: Clone,
+    {
+        self.pending.insert(key.clone(), None);
+    }
+
+    /// Checks if key is there, taking buffered changes into account.
+    pub fn contains_key(&self, key: &K) -> bool {
+        match self.pending.find_node(key) {
+            Some(node) => node.borrow().value.is_some(),
+            None => self.tree.find_node(key).is_some(),
+        }
+    }
+
+    /// Returns copy of value, taking buffered changes into account.
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        match self.pending.find_node(key) {
+            Some(node) => node.borrow().value.clone(),
+            None => self.tree.get(key),
+        }
+    }
+
+    /// Applies all buffered changes to tree at once, through `Tree::apply_batch`.
+    /// Observer that panics is resumed only after all changes are in tree.
+    pub fn commit(self)
+    where
+        V: Clone,
+    {
+        let mut batch = WriteBatch::new();
+        for (key, value) in self.pending.into_entries() {
+            match value {
+                Some(value) => batch.put(key, value),
+                None => batch.delete(key),
+            }
+        }
+        self.tree.apply_batch(batch);
+    }
+
+    /// Discards all buffered changes. Same as dropping transaction.
+    pub fn rollback(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{keys, paper_tree};
+    use crate::Mode;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn reads_see_own_writes() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let mut tx = tree.transaction();
+
+        tx.insert(1, 1);
+        tx.insert(100, 1000);
+        tx.delete(&50);
+        tx.delete(&1);
+
+        assert_eq!(tx.get(&100), Some(1000));
+        assert_eq!(tx.get(&50), None);
+        assert_eq!(tx.get(&1), None);
+        assert_eq!(tx.get(&70), Some(70));
+        assert!(!tx.contains_key(&50));
+        assert!(tx.contains_key(&10));
+
+        tx.insert(50, 5);
+        assert_eq!(tx.get(&50), Some(5));
+    }
+
+    #[test]
+    fn commit_applies() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        let mut tx = tree.transaction();
+        tx.insert(1, 1);
+        tx.insert(100, 1000);
+        tx.delete(&50);
+        tx.delete(&12345);
+        tx.commit();
+
+        assert_eq!(tree.len(), 9);
+        assert_eq!(tree.get(&100), Some(1000));
+        assert_eq!(keys(tree), vec![1, 10, 60, 70, 99, 100, 115, 200, 300]);
+    }
+
+    #[test]
+    fn commit_with_panicking_observer() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+        tree.observe(|_| panic!("observer failed"));
+        let mut tx = tree.transaction();
+        tx.insert(1, 1);
+        tx.insert(100, 1000);
+        tx.delete(&50);
+        tx.delete(&300);
+
+        let result = catch_unwind(AssertUnwindSafe(|| tx.commit()));
+        assert!(result.is_err());
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.get(&100), Some(1000));
+        assert_eq!(keys(tree), vec![1, 10, 60, 70, 99, 100, 115, 200]);
+    }
+
+    #[test]
+    fn rollback_and_drop_discard() {
+        let mut tree = paper_tree(Mode::Unbalanced);
+
+        let mut tx = tree.transaction();
+        tx.insert(1, 1);
+        tx.delete(&50);
+        tx.rollback();
+
+        {
+            let mut tx = tree.transaction();
+            tx.insert(2, 2);
+            tx.delete(&100);
+        }
+
+        assert_eq!(tree.len(), 9);
+        assert_eq!(keys(tree), vec![10, 50, 60, 70, 99, 100, 115, 200, 300]);
+    }
+}
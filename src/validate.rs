@@ -0,0 +1,239 @@
+use std::cell::RefCell;
+use std::cmp::Ord;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{Link, Tree, TreeNode};
+
+/// Broken invariant found by `Tree::validate`, with key of the node where it was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvariantError<K> {
+    /// Root node has a parent.
+    RootHasParent { key: K },
+    /// Node is on the wrong side of one of its ancestors.
+    Unordered { key: K },
+    /// Node is a child of its parent, but its parent link points elsewhere.
+    BrokenParentLink { key: K },
+    /// Node is reachable twice from root.
+    Cycle { key: K },
+    /// Subtree size stored in node is not the number of nodes in subtree.
+    WrongCount {
+        key: K,
+        stored: usize,
+        counted: usize,
+    },
+    /// Tree size is not the number of nodes.
+    WrongSize { size: usize, counted: usize },
+}
+
+impl<K: fmt::Debug> fmt::Display for InvariantError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantError::RootHasParent { key } => write!(f, "root {:?} has a parent", key),
+            InvariantError::Unordered { key } => write!(f, "node {:?} is out of order", key),
+            InvariantError::BrokenParentLink { key } => {
+                write!(f, "node {:?} does not link back to its parent", key)
+            }
+            InvariantError::Cycle { key } => write!(f, "node {:?} is reachable twice", key),
+            InvariantError::WrongCount {
+                key,
+                stored,
+                counted,
+            } => write!(
+                f,
+                "node {:?} says its subtree has {} nodes, but it has {}",
+                key, stored, counted
+            ),
+            InvariantError::WrongSize { size, counted } => {
+                write!(f, "tree says it has {} nodes, but it has {}", size, counted)
+            }
+        }
+    }
+}
+
+impl<K: fmt::Debug> Error for InvariantError<K> {}
+
+/// Node along with nodes whose keys bound its key.
+type Bounded<K, V> = (Link<K, V>, Link<K, V>, Rc<RefCell<TreeNode<K, V>>>);
+
+impl<K: Ord + Clone, V> Tree<K, V> {
+    /// Checks links, order and sizes of all nodes.
+    /// Meant for tests and debugging, walks whole tree.
+    pub fn validate(&self) -> Result<(), InvariantError<K>> {
+        let root = match &self.root {
+            None if self.size == 0 => return Ok(()),
+            None => {
+                return Err(InvariantError::WrongSize {
+                    size: self.size,
+                    counted: 0,
+                })
+            }
+            Some(root) => root,
+        };
+        if root.borrow().parent.is_some() {
+            return Err(InvariantError::RootHasParent {
+                key: root.borrow().key.clone(),
+            });
+        }
+
+        // Pre-order walk
+        let mut visited = HashSet::new();
+        let mut order = vec![];
+        let mut stack: Vec<Bounded<K, V>> = vec![(None, None, Rc::clone(root))];
+        while let Some((lower, upper, node)) = stack.pop() {
+            let tree_node = node.borrow();
+            let key = || tree_node.key.clone();
+            if !visited.insert(Rc::as_ptr(&node)) {
+                return Err(InvariantError::Cycle { key: key() });
+            }
+            let above_lower = lower
+                .as_ref()
+                .map_or(true, |l| l.borrow().key < tree_node.key);
+            let below_upper = upper
+                .as_ref()
+                .map_or(true, |u| u.borrow().key > tree_node.key);
+            if !above_lower || !below_upper {
+                return Err(InvariantError::Unordered { key: key() });
+            }
+
+            for child in tree_node.left.iter().chain(tree_node.right.iter()) {
+                let child_node = child.borrow();
+                let parent = child_node.parent.as_ref();
+                if !parent.is_some_and(|parent| Rc::ptr_eq(parent, &node)) {
+                    return Err(InvariantError::BrokenParentLink {
+                        key: child_node.key.clone(),
+                    });
+                }
+            }
+
+            if let Some(right) = &tree_node.right {
+                stack.push((Some(Rc::clone(&node)), upper, Rc::clone(right)));
+            }
+            if let Some(left) = &tree_node.left {
+                stack.push((lower, Some(Rc::clone(&node)), Rc::clone(left)));
+            }
+            drop(tree_node);
+            order.push(node);
+        }
+
+        // Children come after parents in pre-order, so their counts are known first
+        let mut counted = HashMap::new();
+        for node in order.iter().rev() {
+            let tree_node = node.borrow();
+            let count = 1 + tree_node
+                .left
+                .iter()
+                .chain(tree_node.right.iter())
+                .map(|child| counted[&Rc::as_ptr(child)])
+                .sum::<usize>();
+            if count != tree_node.count {
+                return Err(InvariantError::WrongCount {
+                    key: tree_node.key.clone(),
+                    stored: tree_node.count,
+                    counted: count,
+                });
+            }
+            counted.insert(Rc::as_ptr(node), count);
+        }
+
+        if order.len() != self.size {
+            return Err(InvariantError::WrongSize {
+                size: self.size,
+                counted: order.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InvariantError;
+    use crate::tests::paper_tree;
+    use crate::{Mode, Tree};
+    use std::rc::Rc;
+
+    #[test]
+    fn valid_after_changes() {
+        for mode in &[Mode::Unbalanced, Mode::Splay] {
+            let mut tree = Tree::with_mode(*mode);
+            assert_eq!(tree.validate(), Ok(()));
+            for key in &[100, 50, 10, 70, 60, 99, 200, 300, 115, 1, 2, 3] {
+                tree.insert(*key, *key);
+                assert_eq!(tree.validate(), Ok(()));
+            }
+            for key in &[50, 100, 3, 300, 7, 70, 1] {
+                tree.delete(key);
+                assert_eq!(tree.validate(), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn broken_links() {
+        let tree = paper_tree(Mode::Unbalanced);
+        let root = tree.root.clone().unwrap();
+        let left = root.borrow().left.clone().unwrap();
+        let right = root.borrow().right.clone().unwrap();
+
+        left.borrow_mut().parent = Some(Rc::clone(&right));
+        assert_eq!(
+            tree.validate(),
+            Err(InvariantError::BrokenParentLink { key: 50 })
+        );
+        left.borrow_mut().parent = Some(Rc::clone(&root));
+
+        root.borrow_mut().parent = Some(Rc::clone(&right));
+        assert_eq!(
+            tree.validate(),
+            Err(InvariantError::RootHasParent { key: 100 })
+        );
+        root.borrow_mut().parent = None;
+
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn cycle() {
+        // Parent links can not tell that 10 is both children of 50
+        let tree = paper_tree(Mode::Unbalanced);
+        let node = tree.find_node(&50).unwrap();
+        let left = node.borrow().left.clone();
+        node.borrow_mut().right = left;
+        assert_eq!(tree.validate(), Err(InvariantError::Cycle { key: 10 }));
+    }
+
+    #[test]
+    fn order_count_and_size() {
+        let tree = paper_tree(Mode::Unbalanced);
+        let node = tree.find_node(&60).unwrap();
+        node.borrow_mut().key = 65;
+        assert_eq!(tree.validate(), Ok(()));
+        node.borrow_mut().key = 75;
+        assert_eq!(tree.validate(), Err(InvariantError::Unordered { key: 75 }));
+        node.borrow_mut().key = 60;
+
+        tree.find_node(&70).unwrap().borrow_mut().count = 2;
+        assert_eq!(
+            tree.validate(),
+            Err(InvariantError::WrongCount {
+                key: 70,
+                stored: 2,
+                counted: 3
+            })
+        );
+        tree.find_node(&70).unwrap().borrow_mut().count = 3;
+
+        let mut tree = tree;
+        tree.size = 10;
+        assert_eq!(
+            tree.validate(),
+            Err(InvariantError::WrongSize {
+                size: 10,
+                counted: 9
+            })
+        );
+    }
+}
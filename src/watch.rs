@@ -0,0 +1,115 @@
+use std::cmp::Ord;
+use std::ops::RangeBounds;
+use std::sync::mpsc::{self, Receiver};
+
+use crate::{Change, Event, Tree};
+
+impl<K: Ord + Clone + 'static, V: Clone + 'static> Tree<K, V> {
+    /// Returns channel receiving copies of changes of keys in given range, in order they happen.
+    /// Watch is dropped from tree on the first change after receiver is dropped.
+    pub fn watch<R>(&mut self, range: R) -> Receiver<Change<K, V>>
+    where
+        R: RangeBounds<K>,
+    {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        let (sender, receiver) = mpsc::channel();
+
+        self.observe_while(move |event| {
+            let key = match event {
+                Event::Inserted { key, .. } => *key,
+                Event::Replaced { key, .. } => *key,
+                Event::Removed { key, .. } => *key,
+            };
+            // Sending fails only when receiver is gone
+            !range.contains(key) || sender.send(Change::of(event)).is_ok()
+        });
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Change, Tree, WriteBatch};
+
+    #[test]
+    fn only_watched_range() {
+        let mut tree = Tree::new();
+        let low = tree.watch(..10);
+        let middle = tree.watch(10..=20);
+
+        tree.insert(5, 5);
+        tree.insert(10, 10);
+        tree.insert(20, 20);
+        tree.insert(21, 21);
+        tree.insert(10, 11);
+        tree.delete(&5);
+
+        let low: Vec<_> = low.try_iter().collect();
+        assert_eq!(
+            low,
+            vec![
+                Change::Inserted { key: 5, value: 5 },
+                Change::Removed { key: 5, value: 5 },
+            ]
+        );
+        let middle: Vec<_> = middle.try_iter().collect();
+        assert_eq!(
+            middle,
+            vec![
+                Change::Inserted { key: 10, value: 10 },
+                Change::Inserted { key: 20, value: 20 },
+                Change::Replaced {
+                    key: 10,
+                    old: 10,
+                    new: 11
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn bulk_changes() {
+        let mut tree = Tree::new();
+        for i in 0..10 {
+            tree.insert(i, i);
+        }
+        let receiver = tree.watch(3..6);
+
+        let mut batch = WriteBatch::new();
+        batch.put(4, 40);
+        batch.put(8, 80);
+        tree.apply_batch(batch);
+        tree.retain(|key, _| key % 2 == 0);
+
+        let keys: Vec<_> = receiver
+            .try_iter()
+            .map(|change| match change {
+                Change::Inserted { key, .. } => key,
+                Change::Replaced { key, .. } => key,
+                Change::Removed { key, .. } => key,
+            })
+            .collect();
+        assert_eq!(keys, vec![4, 3, 5]);
+    }
+
+    #[test]
+    fn dropped_receiver_is_cleaned_up() {
+        let mut tree = Tree::new();
+        let receiver = tree.watch(..);
+        let kept = tree.watch(..);
+        drop(receiver);
+
+        tree.insert(1, 1);
+        assert_eq!(tree.observers.list.len(), 1);
+        tree.insert(2, 2);
+        assert_eq!(kept.try_iter().count(), 2);
+
+        // Changes outside of range do not clean up, there is nothing to send
+        let outside = tree.watch(100..);
+        drop(outside);
+        tree.insert(3, 3);
+        assert_eq!(tree.observers.list.len(), 2);
+        tree.insert(100, 100);
+        assert_eq!(tree.observers.list.len(), 1);
+    }
+}